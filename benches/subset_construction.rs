@@ -0,0 +1,92 @@
+//! 部分集合構成法の速さを、遷移規則を毎回なめる素朴な実装と比べる
+//!
+//! `(a|b)*a(a|b){n}`は末尾からn+1文字目が`a`の文字列を表し、DFAの状態数が2^(n+1)になる。
+//! 素朴な実装ではnが大きいと時間がかかりすぎるので、比べるのは`NAIVE_MAX_N`までにし、
+//! それより大きいnでは索引を使う実装だけを測る。
+//!
+//! n=20ではDFAの遷移規則が約420万になり、1回の変換に50秒ほどかかる。反復回数が最小になるように
+//! `SamplingMode::Flat`で測るが、それでもn=20だけで10分ほどかかる。
+
+use std::collections::{BTreeSet, VecDeque};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, SamplingMode};
+use regend::{
+    dfa,
+    nfa::{GlobalEnv, Label, Nfa, State},
+    parser,
+    regexpr::get_alphabets_with,
+};
+
+/// 素朴な実装と比べるnの上限
+const NAIVE_MAX_N: usize = 10;
+
+/// 索引を使わない部分集合構成法。遷移を求めるたびに遷移規則をすべてなめる
+fn naive_subset_construction(nfa: &Nfa, alphabets: &[char]) -> usize {
+    let edge = |s: State, label: Label| {
+        nfa.rules()
+            .iter()
+            .filter(move |r| r.from == s && r.label == label)
+            .map(|r| r.to)
+    };
+    let closure = |states: BTreeSet<State>| {
+        let mut worklist: Vec<State> = states.iter().copied().collect();
+        let mut t = states;
+        while let Some(s) = worklist.pop() {
+            for u in edge(s, Label::Epsilon) {
+                if t.insert(u) {
+                    worklist.push(u);
+                }
+            }
+        }
+        t
+    };
+
+    let mut seen = BTreeSet::new();
+    let mut queue = VecDeque::new();
+    let mut rules = 0;
+    let start = closure(BTreeSet::from([nfa.start]));
+    seen.insert(start.clone());
+    queue.push_back(start);
+    while let Some(states) = queue.pop_front() {
+        for c in alphabets {
+            let next = closure(
+                states
+                    .iter()
+                    .flat_map(|s| edge(*s, Label::Symbol(*c)))
+                    .collect(),
+            );
+            rules += 1;
+            if seen.insert(next.clone()) {
+                queue.push_back(next);
+            }
+        }
+    }
+    rules
+}
+
+fn subset_construction(c: &mut Criterion) {
+    let mut group = c.benchmark_group("subset_construction");
+    group.sample_size(10).sampling_mode(SamplingMode::Flat);
+    for n in [6, 8, 10, 14, 16, 20] {
+        let r = parser::parse(&format!("(a|b)*a(a|b){{{n}}}")).unwrap();
+        let alphabets = get_alphabets_with(&[&r], &[]);
+        let nfa = r.to_nfa(&mut GlobalEnv::new(&alphabets));
+
+        group.bench_with_input(BenchmarkId::new("indexed", n), &n, |b, _| {
+            b.iter(|| nfa.to_dfa(&alphabets))
+        });
+        if n > NAIVE_MAX_N {
+            continue;
+        }
+
+        let dfa: dfa::Dfa = nfa.to_dfa(&alphabets);
+        assert_eq!(dfa.rules.len(), naive_subset_construction(&nfa, &alphabets));
+        group.bench_with_input(BenchmarkId::new("naive", n), &n, |b, _| {
+            b.iter(|| naive_subset_construction(&nfa, &alphabets))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, subset_construction);
+criterion_main!(benches);
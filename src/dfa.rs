@@ -7,6 +7,10 @@ use itertools::Itertools;
 
 use wasm_bindgen::prelude::*;
 
+use crate::{error::Error, nfa::Budget};
+
+pub mod dense;
+
 pub type State = i32;
 
 #[wasm_bindgen]
@@ -26,13 +30,23 @@ impl Display for Rule {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub struct Dfa {
     pub start: State,
     pub finish_states: BTreeSet<State>,
     pub rules: BTreeSet<Rule>,
 }
 
+/// DFAに文字列を読み込ませた結果
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct RunResult {
+    pub accepted: bool,
+    /// 読み込んだ文字ごとの(遷移元, 文字, 遷移先)
+    pub path: Vec<(State, char, State)>,
+    /// 遷移規則がなく行き詰まった(状態, 文字)。行き詰まった場合は受理しない
+    pub stuck_at: Option<(State, char)>,
+}
+
 pub type Table = BTreeMap<State, (bool, bool, BTreeMap<char, State>)>;
 
 impl Display for Dfa {
@@ -63,15 +77,133 @@ impl Display for Dfa {
 }
 
 impl Dfa {
-    pub fn from_table(table: &Table) -> Self {
+    /// 開始状態と、遷移規則に現れるすべての状態
+    pub fn states(&self) -> BTreeSet<State> {
+        let mut states = BTreeSet::new();
+        states.insert(self.start);
+        for rule in &self.rules {
+            states.insert(rule.from);
+            states.insert(rule.to);
+        }
+        states
+    }
+
+    /// 遷移規則に現れるすべての文字
+    pub fn alphabets(&self) -> BTreeSet<char> {
+        self.rules.iter().map(|r| r.alphabet).collect()
+    }
+
+    /// 遷移元と遷移先が同じ遷移規則を1つにまとめた(遷移元, 遷移先, 文字の一覧)の列
+    pub fn grouped_rules(&self) -> Vec<(State, State, Vec<char>)> {
+        let mut groups: BTreeMap<(State, State), Vec<char>> = BTreeMap::new();
+        for rule in &self.rules {
+            groups
+                .entry((rule.from, rule.to))
+                .or_default()
+                .push(rule.alphabet);
+        }
+        groups
+            .into_iter()
+            .map(|((from, to), alphabets)| (from, to, alphabets))
+            .collect()
+    }
+
+    /// 状態`from`から文字`c`で遷移する先の状態。遷移規則がなければ`None`
+    pub fn next(&self, from: State, c: char) -> Option<State> {
+        let lower = Rule {
+            from,
+            alphabet: c,
+            to: State::MIN,
+        };
+        self.rules
+            .range(lower..)
+            .next()
+            .filter(|r| r.from == from && r.alphabet == c)
+            .map(|r| r.to)
+    }
+
+    /// 文字の集合`alphabets`と、遷移規則に現れる文字の和集合の上で完全なDFAにする
+    ///
+    /// 遷移規則が欠けているところには、新しく追加した死に状態への遷移を補う。
+    /// 欠けている遷移規則がなければ死に状態は追加しない。
+    pub fn complete(&self, alphabets: &[char]) -> Dfa {
+        let mut all_alphabets = self.alphabets();
+        all_alphabets.extend(alphabets);
+        let states = self.states();
+        let sink = states.last().unwrap() + 1;
+
+        let mut rules = self.rules.clone();
+        for from in states {
+            for c in &all_alphabets {
+                if self.next(from, *c).is_none() {
+                    rules.insert(Rule {
+                        from,
+                        alphabet: *c,
+                        to: sink,
+                    });
+                }
+            }
+        }
+        if rules.len() != self.rules.len() {
+            for c in &all_alphabets {
+                rules.insert(Rule {
+                    from: sink,
+                    alphabet: *c,
+                    to: sink,
+                });
+            }
+        }
+
+        Dfa {
+            start: self.start,
+            finish_states: self.finish_states.clone(),
+            rules,
+        }
+    }
+
+    /// 文字の集合`alphabets`(と遷移規則に現れる文字)の上での補集合を受理するDFA
+    ///
+    /// `complete`で完全にしてから、受理状態とそれ以外を入れ替える。
+    pub fn complement(&self, alphabets: &[char]) -> Dfa {
+        let complete = self.complete(alphabets);
+        let finish_states = complete
+            .states()
+            .difference(&complete.finish_states)
+            .copied()
+            .collect();
+        Dfa {
+            finish_states,
+            ..complete
+        }
+    }
+
+    /// `complement`と同じだが、完全にするために調べる(状態, 文字)の組を遷移の計算として、
+    /// 追加する死に状態をDFAの状態として`budget`で数える
+    pub fn complement_with_budget(
+        &self,
+        alphabets: &[char],
+        budget: &mut Budget,
+    ) -> Result<Dfa, Error> {
+        let mut all_alphabets = self.alphabets();
+        all_alphabets.extend(alphabets);
+        let states = self.states().len();
+        budget.add_steps(states * all_alphabets.len(), 0)?;
+        let complement = self.complement(alphabets);
+        if complement.states().len() > states {
+            budget.add_state(0)?;
+        }
+        Ok(complement)
+    }
+
+    pub fn from_table(table: &Table) -> Result<Self, Error> {
         let mut start = None;
         let mut finish_states = BTreeSet::new();
         let mut rules = BTreeSet::new();
 
         for (from, (is_finish, is_start, row)) in table {
             if *is_start {
-                if start.is_some() {
-                    panic!("複数の開始状態がある")
+                if let Some(start) = start {
+                    return Err(Error::MultipleStartStates(start, *from));
                 }
                 start = Some(*from);
             }
@@ -88,45 +220,35 @@ impl Dfa {
             }
         }
 
-        Self {
-            start: start.expect("開始状態がない"),
+        Ok(Self {
+            start: start.ok_or(Error::NoStartState)?,
             finish_states,
             rules,
-        }
+        })
     }
 
-    pub fn run(&self, input: &str) -> State {
-        print!("\"{}\"\t", input);
+    /// 文字列`input`を読み込ませ、受理するかどうかと通った経路を返す
+    ///
+    /// 1文字ごとに遷移規則を探すので、多くの文字列を読み込ませるときは`compile`したものを使う。
+    pub fn run(&self, input: &str) -> RunResult {
+        let mut path = vec![];
         let mut current = self.start;
-        print!("{}", current);
-
         for c in input.chars() {
-            let mut rule = None;
-            for r in &self.rules {
-                if r.alphabet == c && r.from == current {
-                    rule = Some(r);
-                    break;
-                }
-            }
-            if let Some(rule) = rule {
-                current = rule.to;
-                print!("->{}", current);
-            } else {
-                panic!("no rule from={}, alphabet={}. DFA is invalid.", current, c);
-            }
+            let Some(to) = self.next(current, c) else {
+                return RunResult {
+                    accepted: false,
+                    path,
+                    stuck_at: Some((current, c)),
+                };
+            };
+            path.push((current, c, to));
+            current = to;
         }
-
-        const GREEN: &str = "\x1b[32m";
-        const RED: &str = "\x1b[31m";
-        const RESET: &str = "\x1b[0m";
-
-        if self.finish_states.contains(&current) {
-            println!("\t{GREEN}Accepted{RESET}");
-        } else {
-            println!("\t{RED}Rejected{RESET}");
+        RunResult {
+            accepted: self.finish_states.contains(&current),
+            path,
+            stuck_at: None,
         }
-
-        current
     }
 
     pub fn to_table(&self) -> Table {
@@ -145,20 +267,20 @@ impl Dfa {
         table
     }
 
-    pub fn print_table(table: &Table) {
-        for (state, (is_finish, is_start, row)) in table {
-            if *is_start {
-                print!("{state}:");
-                print!("{},", if *is_finish { "f" } else { "c" });
-                println!("{}", row.values().map(|s| format!("{s}")).join(","));
-            }
-        }
-        for (state, (is_finish, is_start, row)) in table {
-            if !is_start {
-                print!("{state}:");
-                print!("{},", if *is_finish { "f" } else { "c" });
-                println!("{}", row.values().map(|s| format!("{s}")).join(","));
-            }
+    /// 状態遷移表を、1行に1つの状態を`状態:f(受理状態)かc,遷移先,...`の形式で書いた文字列にする。
+    /// 開始状態が先頭の行になる
+    pub fn format_table(table: &Table) -> String {
+        let mut s = String::new();
+        let starts = table.iter().filter(|(_, (_, is_start, _))| *is_start);
+        let others = table.iter().filter(|(_, (_, is_start, _))| !*is_start);
+        for (state, (is_finish, _, row)) in starts.chain(others) {
+            s.push_str(&format!("{state}:"));
+            s.push_str(&format!("{},", if *is_finish { "f" } else { "c" }));
+            s.push_str(&format!(
+                "{}\n",
+                row.values().map(|s| format!("{s}")).join(",")
+            ));
         }
+        s
     }
 }
@@ -0,0 +1,139 @@
+//! 遷移を表引きで求めるDFA
+
+use std::collections::HashMap;
+
+use super::{Dfa, RunResult, State};
+
+/// 遷移規則を(状態 × 文字クラス)の表にしたDFA。`Dfa::compile`で作る
+///
+/// どの状態からも同じ遷移先になる文字は1つの文字クラスにまとめ、表の列を減らす。
+/// 状態は小さい順に0から数え直した添字で扱い、1文字ごとの遷移は表を1回引くだけで求まる。
+#[derive(Debug, Clone)]
+pub struct DenseDfa {
+    /// 添字 => 状態
+    states: Vec<State>,
+    /// 開始状態の添字
+    start: usize,
+    /// 添字 => 受理状態かどうか
+    finish: Vec<bool>,
+    /// ASCII文字 => 文字クラス
+    ascii: [Option<usize>; 128],
+    /// ASCII以外の文字 => 文字クラス
+    others: HashMap<char, usize>,
+    /// 文字クラスの数
+    class_count: usize,
+    /// 添字`i`の状態から文字クラス`k`での遷移先は`table[i * class_count + k]`
+    table: Vec<Option<usize>>,
+}
+
+impl Dfa {
+    /// 遷移規則を表にまとめ、多くの文字列を速く読み込ませられるようにする
+    pub fn compile(&self) -> DenseDfa {
+        let states: Vec<State> = self.states().into_iter().collect();
+        let index = |s: State| states.binary_search(&s).unwrap();
+
+        // 文字 => 各状態からの遷移先の列。列が同じ文字は同じ文字クラスにする
+        let mut columns: HashMap<char, Vec<Option<usize>>> = HashMap::new();
+        for rule in &self.rules {
+            columns
+                .entry(rule.alphabet)
+                .or_insert_with(|| vec![None; states.len()])[index(rule.from)] =
+                Some(index(rule.to));
+        }
+        let mut alphabets: Vec<char> = columns.keys().copied().collect();
+        alphabets.sort_unstable();
+
+        let mut classes: HashMap<&Vec<Option<usize>>, usize> = HashMap::new();
+        let mut class_columns = vec![];
+        let mut ascii = [None; 128];
+        let mut others = HashMap::new();
+        for c in &alphabets {
+            let column = &columns[c];
+            let class = *classes.entry(column).or_insert_with(|| {
+                class_columns.push(column);
+                class_columns.len() - 1
+            });
+            if c.is_ascii() {
+                ascii[*c as usize] = Some(class);
+            } else {
+                others.insert(*c, class);
+            }
+        }
+
+        let class_count = class_columns.len();
+        let mut table = vec![None; states.len() * class_count];
+        for (k, column) in class_columns.iter().enumerate() {
+            for (i, to) in column.iter().enumerate() {
+                table[i * class_count + k] = *to;
+            }
+        }
+
+        DenseDfa {
+            start: index(self.start),
+            finish: states
+                .iter()
+                .map(|s| self.finish_states.contains(s))
+                .collect(),
+            states,
+            ascii,
+            others,
+            class_count,
+            table,
+        }
+    }
+}
+
+impl DenseDfa {
+    /// 文字クラスの数
+    pub fn class_count(&self) -> usize {
+        self.class_count
+    }
+
+    /// 文字`c`の文字クラス。遷移規則に現れない文字なら`None`
+    fn class(&self, c: char) -> Option<usize> {
+        if c.is_ascii() {
+            self.ascii[c as usize]
+        } else {
+            self.others.get(&c).copied()
+        }
+    }
+
+    /// 添字`i`の状態から文字`c`で遷移する先の添字
+    fn next(&self, i: usize, c: char) -> Option<usize> {
+        self.table[i * self.class_count + self.class(c)?]
+    }
+
+    /// 文字列`input`を受理するかどうか。経路は記録しない
+    pub fn accepts(&self, input: &str) -> bool {
+        let mut current = self.start;
+        for c in input.chars() {
+            match self.next(current, c) {
+                Some(to) => current = to,
+                None => return false,
+            }
+        }
+        self.finish[current]
+    }
+
+    /// 文字列`input`を読み込ませ、受理するかどうかと通った経路を返す。結果は`Dfa::run`と同じ
+    pub fn run(&self, input: &str) -> RunResult {
+        let mut path = vec![];
+        let mut current = self.start;
+        for c in input.chars() {
+            let Some(to) = self.next(current, c) else {
+                return RunResult {
+                    accepted: false,
+                    path,
+                    stuck_at: Some((self.states[current], c)),
+                };
+            };
+            path.push((self.states[current], c, self.states[to]));
+            current = to;
+        }
+        RunResult {
+            accepted: self.finish[current],
+            path,
+            stuck_at: None,
+        }
+    }
+}
@@ -0,0 +1,78 @@
+use std::fmt::Display;
+
+use wasm_bindgen::prelude::*;
+
+/// 正規表現のパースエラーの診断情報
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// 問題のある箇所の開始位置(バイト単位)
+    pub start: usize,
+    /// 問題のある箇所の終了位置(バイト単位、この位置を含まない)
+    pub end: usize,
+    /// 問題のある箇所の列番号(1始まり、文字単位)
+    pub column: usize,
+    pub message: String,
+    /// 修正方法の提案
+    pub help: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn new(source: &str, start: usize, end: usize, message: impl Into<String>) -> Self {
+        Self {
+            start,
+            end,
+            column: source[..start].chars().count() + 1,
+            message: message.into(),
+            help: None,
+        }
+    }
+
+    pub fn with_help(mut self, help: impl Into<String>) -> Self {
+        self.help = Some(help.into());
+        self
+    }
+}
+
+#[wasm_bindgen]
+impl Diagnostic {
+    /// 元の正規表現の下に、問題のある箇所を`^`で示した複数行の文字列にする
+    pub fn render(&self, source: &str) -> String {
+        let indent = " ".repeat(width(&source[..self.start]));
+        let carets = "^".repeat(width(&source[self.start..self.end]).max(1));
+        let mut s = format!("{}\n  {}\n  {}{}", self, source, indent, carets);
+        if let Some(help) = &self.help {
+            s.push_str(&format!("\n  ヒント: {}", help));
+        }
+        s
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!(
+            "正規表現のパースエラー (列{}): {}",
+            self.column, self.message
+        ))
+    }
+}
+
+/// 端末に表示したときのおおよその幅。全角文字を2、それ以外を1として数える
+pub(crate) fn width(s: &str) -> usize {
+    s.chars().map(char_width).sum()
+}
+
+/// 端末に表示したときの1文字のおおよその幅
+pub(crate) fn char_width(c: char) -> usize {
+    match c as u32 {
+        0x1100..=0x115F
+        | 0x2E80..=0xA4CF
+        | 0xAC00..=0xD7A3
+        | 0xF900..=0xFAFF
+        | 0xFE30..=0xFE4F
+        | 0xFF00..=0xFF60
+        | 0xFFE0..=0xFFE6
+        | 0x20000..=0x3FFFD => 2,
+        _ => 1,
+    }
+}
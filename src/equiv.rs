@@ -0,0 +1,100 @@
+use std::collections::{BTreeSet, HashMap, VecDeque};
+
+use crate::{
+    dfa::{Dfa, State},
+    error::Error,
+    nfa::{Budget, Limits},
+    regexpr::{get_alphabets_with, RegExpr},
+};
+
+/// 2つの言語が等しいかどうかの判定結果
+#[derive(Debug, PartialEq, Eq)]
+pub enum Equivalence {
+    Equivalent,
+    Different {
+        /// 片方だけが受理する文字列のうち、最短のもの
+        witness: String,
+        /// `witness`を受理するのが左辺かどうか
+        accepted_by_left: bool,
+    },
+}
+
+/// 2つのDFAの状態の組。`None`は遷移規則がなく行き詰まったことを表す
+type Pair = (Option<State>, Option<State>);
+
+impl Dfa {
+    /// 2つのDFAが同じ言語を受理するかどうか調べる
+    ///
+    /// 文字の集合は両者の和集合で揃え、遷移規則がない場合はその文字列を受理しないものとして扱う。
+    /// 異なる場合は、2つのDFAを同時に幅優先で辿って見つけた最短の反例を返す。
+    pub fn equivalent(&self, other: &Dfa) -> Equivalence {
+        let alphabets: BTreeSet<char> = self
+            .alphabets()
+            .union(&other.alphabets())
+            .copied()
+            .collect();
+        let accepts =
+            |dfa: &Dfa, state: Option<State>| state.is_some_and(|s| dfa.finish_states.contains(&s));
+
+        // (左の状態, 右の状態) => (直前の組, 文字)
+        let mut visited: HashMap<Pair, Option<(Pair, char)>> = HashMap::new();
+        let mut queue = VecDeque::new();
+        let start = (Some(self.start), Some(other.start));
+        visited.insert(start, None);
+        queue.push_back(start);
+
+        while let Some(pair @ (left, right)) = queue.pop_front() {
+            let accepted_by_left = accepts(self, left);
+            if accepted_by_left != accepts(other, right) {
+                let mut witness = vec![];
+                let mut current = pair;
+                while let Some((prev, c)) = visited[&current] {
+                    witness.push(c);
+                    current = prev;
+                }
+                return Equivalence::Different {
+                    witness: witness.into_iter().rev().collect(),
+                    accepted_by_left,
+                };
+            }
+
+            for c in &alphabets {
+                let next = (
+                    left.and_then(|s| self.next(s, *c)),
+                    right.and_then(|s| other.next(s, *c)),
+                );
+                if next == (None, None) || visited.contains_key(&next) {
+                    continue;
+                }
+                visited.insert(next, Some((pair, *c)));
+                queue.push_back(next);
+            }
+        }
+
+        Equivalence::Equivalent
+    }
+}
+
+impl RegExpr {
+    /// 2つの正規表現が同じ言語を表すかどうか調べる。
+    /// 否定の文字クラスや`.`は、両者に現れる文字と`alphabet`を合わせた文字の集合の上で考える
+    pub fn equivalent(&self, other: &RegExpr, alphabet: &[char]) -> Equivalence {
+        let alphabets = get_alphabets_with(&[self, other], alphabet);
+        self.to_dfa(&alphabets)
+            .equivalent(&other.to_dfa(&alphabets))
+    }
+
+    /// `equivalent`と同じだが、両辺のDFAを合わせて`limits`を超えたら中断する
+    pub fn equivalent_with_limits(
+        &self,
+        other: &RegExpr,
+        alphabet: &[char],
+        limits: Limits,
+    ) -> Result<Equivalence, Error> {
+        let alphabets = get_alphabets_with(&[self, other], alphabet);
+        let mut budget = Budget::new(limits);
+        let left = self.to_dfa_with_budget(&alphabets, &mut budget)?;
+        let right = other.to_dfa_with_budget(&alphabets, &mut budget)?;
+        Ok(left.equivalent(&right))
+    }
+}
@@ -0,0 +1,78 @@
+use std::fmt::Display;
+
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    dfa::State,
+    diagnostic::Diagnostic,
+    nfa::Limits,
+    render::{Format, Graph},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// 正規表現のパースエラー
+    Parse(Diagnostic),
+    /// 状態遷移表に開始状態がない
+    NoStartState,
+    /// 状態遷移表に開始状態が複数ある
+    MultipleStartStates(State, State),
+    /// その形式では出力できない図
+    UnsupportedGraph { format: Format, graph: Graph },
+    /// 変換の途中で作るDFAが上限を超えたので中断した
+    StateExplosion {
+        limits: Limits,
+        /// 中断するまでに作ったDFAの状態の数。共通部分や補集合のために作ったものも含む
+        states: usize,
+        /// 中断するまでに遷移を計算した回数
+        steps: usize,
+        /// 作ったが、まだ遷移を計算し終えていない状態の数
+        pending: usize,
+    },
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Parse(d) => d.fmt(f),
+            Error::NoStartState => f.write_str("開始状態がない"),
+            Error::MultipleStartStates(a, b) => {
+                f.write_fmt(format_args!("複数の開始状態がある: {}, {}", a, b))
+            }
+            Error::UnsupportedGraph { format, graph } => f.write_fmt(format_args!(
+                "{:?}形式では{:?}を出力できない",
+                format, graph
+            )),
+            Error::StateExplosion {
+                limits,
+                states,
+                steps,
+                pending,
+            } => {
+                let limit =
+                    |max: Option<usize>| max.map_or("なし".to_string(), |m| m.to_string());
+                f.write_fmt(format_args!(
+                    "オートマトンが大きくなりすぎるので変換を中断した: 状態{}個(うち未処理{}個)、遷移の計算{}回 (上限: 状態{}、遷移の計算{})",
+                    states,
+                    pending,
+                    steps,
+                    limit(limits.max_states),
+                    limit(limits.max_steps)
+                ))
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<Error> for JsValue {
+    fn from(value: Error) -> Self {
+        match value {
+            Error::Parse(d) => d.into(),
+            e => JsError::new(&e.to_string()).into(),
+        }
+    }
+}
@@ -1,34 +1,114 @@
 use std::collections::BTreeSet;
 
-use itertools::Itertools;
-use nfa::GlobalEnv;
 use wasm_bindgen::prelude::*;
 
-// When the `wee_alloc` feature is enabled, use `wee_alloc` as the global
-// allocator.
-#[cfg(feature = "wee_alloc")]
-#[global_allocator]
-static ALLOC: wee_alloc::WeeAlloc = wee_alloc::WeeAlloc::INIT;
-
 pub mod dfa;
+pub mod diagnostic;
+pub mod equiv;
+pub mod error;
+pub mod minimize;
 pub mod nfa;
 pub mod parser;
+pub mod product;
 pub mod regexpr;
+pub mod render;
+
+pub use error::{Error, Result};
+
+use regexpr::get_alphabets_with;
+
+/// 変換の上限。`None`なら上限なし
+fn limits(max_states: Option<usize>, max_steps: Option<usize>) -> nfa::Limits {
+    nfa::Limits {
+        max_states,
+        max_steps,
+    }
+}
 
+/// 正規表現をDFAに変換する。`alphabet`は否定の文字クラスや`.`が表す文字の集合に加える文字で、
+/// `a-z`のように範囲でも書ける。
+/// DFAの状態数が`max_states`を、遷移の計算回数が`max_steps`を超えたら変換を中断してエラーを返す
 #[wasm_bindgen]
-pub fn str_to_dfa(s: &str) -> Dfa {
-    let regex = parser::parse_expr_until_end(s).unwrap().1;
-    let mut env = GlobalEnv::default();
-    let nfa = regex.to_nfa(&mut env);
-    let dfa = nfa.to_dfa(&regex.get_alphabets());
-    dfa.into()
+pub fn str_to_dfa(
+    s: &str,
+    alphabet: &str,
+    max_states: Option<usize>,
+    max_steps: Option<usize>,
+) -> Result<Dfa> {
+    let regex = parser::parse(s)?;
+    let alphabets = get_alphabets_with(&[&regex], &parser::parse_alphabet(alphabet)?);
+    let dfa = regex.to_dfa_with_limits(&alphabets, limits(max_states, max_steps))?;
+    Ok(dfa.into())
+}
+
+/// 正規表現から作った図を文字列で出力する
+#[wasm_bindgen]
+pub fn str_to_graph(
+    s: &str,
+    alphabet: &str,
+    graph: render::Graph,
+    format: render::Format,
+    max_states: Option<usize>,
+    max_steps: Option<usize>,
+) -> Result<String> {
+    let regex = parser::parse(s)?;
+    let alphabets = get_alphabets_with(&[&regex], &parser::parse_alphabet(alphabet)?);
+    let mut budget = nfa::Budget::new(limits(max_states, max_steps));
+    let nfa = regex.to_nfa_with_budget(&mut nfa::GlobalEnv::new(&alphabets), &mut budget)?;
+    let (dfa, _) = nfa.subset_construction_with_budget(&alphabets, &mut budget)?;
+    render::render(&regex, &nfa, &dfa, graph, format)
+}
+
+#[wasm_bindgen]
+pub fn str_to_minimization(
+    s: &str,
+    alphabet: &str,
+    max_states: Option<usize>,
+    max_steps: Option<usize>,
+) -> Result<Minimization> {
+    let regex = parser::parse(s)?;
+    let alphabets = get_alphabets_with(&[&regex], &parser::parse_alphabet(alphabet)?);
+    let dfa = regex.to_dfa_with_limits(&alphabets, limits(max_states, max_steps))?;
+    let (minimized, trace) = dfa.minimize_with_trace();
+    Ok(Minimization::new(minimized, trace))
+}
+
+#[wasm_bindgen]
+pub fn check_equivalence(
+    left: &str,
+    right: &str,
+    alphabet: &str,
+    max_states: Option<usize>,
+    max_steps: Option<usize>,
+) -> Result<EquivalenceResult> {
+    let left = parser::parse(left)?;
+    let right = parser::parse(right)?;
+    let alphabet = parser::parse_alphabet(alphabet)?;
+    let equivalence =
+        left.equivalent_with_limits(&right, &alphabet, limits(max_states, max_steps))?;
+    Ok(equivalence.into())
 }
 
 #[wasm_bindgen(getter_with_clone)]
+#[derive(Clone)]
 pub struct Dfa {
     pub start: dfa::State,
     pub states: Vec<DfaState>,
     pub rules: Vec<DfaRule>,
+    dense: dfa::dense::DenseDfa,
+}
+
+#[wasm_bindgen]
+impl Dfa {
+    /// 文字列を読み込ませ、受理するかどうかと通った経路を返す
+    pub fn run(&self, input: &str) -> RunResult {
+        self.dense.run(input).into()
+    }
+
+    /// 文字列を受理するかどうか。経路がいらないときはこちらの方が速い
+    pub fn accepts(&self, input: &str) -> bool {
+        self.dense.accepts(input)
+    }
 }
 
 #[wasm_bindgen]
@@ -46,8 +126,48 @@ pub struct DfaRule {
     pub alphabets: String,
 }
 
+#[wasm_bindgen(getter_with_clone)]
+pub struct RunResult {
+    pub accepted: bool,
+    pub path: Vec<RunStep>,
+    /// 遷移規則がなく行き詰まった状態と文字
+    pub stuck_at: Option<RunStuck>,
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy)]
+pub struct RunStep {
+    pub from: dfa::State,
+    pub alphabet: char,
+    pub to: dfa::State,
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy)]
+pub struct RunStuck {
+    pub state: dfa::State,
+    pub alphabet: char,
+}
+
+impl From<dfa::RunResult> for RunResult {
+    fn from(value: dfa::RunResult) -> Self {
+        Self {
+            accepted: value.accepted,
+            path: value
+                .path
+                .into_iter()
+                .map(|(from, alphabet, to)| RunStep { from, alphabet, to })
+                .collect(),
+            stuck_at: value
+                .stuck_at
+                .map(|(state, alphabet)| RunStuck { state, alphabet }),
+        }
+    }
+}
+
 impl From<dfa::Dfa> for Dfa {
     fn from(value: dfa::Dfa) -> Self {
+        let dense = value.compile();
         let start = value.start;
 
         let mut states_set = BTreeSet::new();
@@ -61,16 +181,11 @@ impl From<dfa::Dfa> for Dfa {
         states.extend(states_set);
 
         let mut rules = Vec::new();
-        for ((from, to), rs) in value
-            .rules
-            .into_iter()
-            .into_group_map_by(|r| (r.from, r.to))
-        {
-            let alphabets: String = rs.iter().map(|r| r.alphabet).collect();
+        for (from, to, alphabets) in value.grouped_rules() {
             rules.push(DfaRule {
                 from,
                 to,
-                alphabets,
+                alphabets: alphabets.into_iter().collect(),
             });
         }
 
@@ -78,6 +193,120 @@ impl From<dfa::Dfa> for Dfa {
             start,
             states,
             rules,
+            dense,
+        }
+    }
+}
+
+#[wasm_bindgen(getter_with_clone)]
+pub struct EquivalenceResult {
+    pub equivalent: bool,
+    /// 片方だけが受理する最短の文字列
+    pub witness: Option<String>,
+    /// `witness`を受理するのが左辺かどうか
+    pub accepted_by_left: bool,
+}
+
+impl From<equiv::Equivalence> for EquivalenceResult {
+    fn from(value: equiv::Equivalence) -> Self {
+        match value {
+            equiv::Equivalence::Equivalent => Self {
+                equivalent: true,
+                witness: None,
+                accepted_by_left: false,
+            },
+            equiv::Equivalence::Different {
+                witness,
+                accepted_by_left,
+            } => Self {
+                equivalent: false,
+                witness: Some(witness),
+                accepted_by_left,
+            },
+        }
+    }
+}
+
+#[wasm_bindgen(getter_with_clone)]
+pub struct Minimization {
+    pub dfa: Dfa,
+    pub classes: Vec<StateClass>,
+    pub initial: Vec<StateBlock>,
+    pub rounds: Vec<MinimizeRound>,
+    pub sink: Option<dfa::State>,
+}
+
+/// 元のDFAの状態と、最小DFAでのその状態
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct StateClass {
+    pub state: dfa::State,
+    pub class: dfa::State,
+}
+
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone)]
+pub struct StateBlock {
+    pub states: Vec<dfa::State>,
+}
+
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone)]
+pub struct MinimizeRound {
+    pub partition: Vec<StateBlock>,
+    pub splitter: StateBlock,
+    pub splits: Vec<MinimizeSplit>,
+}
+
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone)]
+pub struct MinimizeSplit {
+    pub alphabet: char,
+    pub block: StateBlock,
+    pub inside: StateBlock,
+    pub outside: StateBlock,
+}
+
+impl From<BTreeSet<dfa::State>> for StateBlock {
+    fn from(value: BTreeSet<dfa::State>) -> Self {
+        Self {
+            states: value.into_iter().collect(),
+        }
+    }
+}
+
+impl Minimization {
+    fn new(minimized: minimize::Minimized, trace: minimize::Trace) -> Self {
+        let to_blocks = |partition: Vec<BTreeSet<dfa::State>>| -> Vec<StateBlock> {
+            partition.into_iter().map(StateBlock::from).collect()
+        };
+        Self {
+            dfa: minimized.dfa.into(),
+            classes: minimized
+                .classes
+                .into_iter()
+                .map(|(state, class)| StateClass { state, class })
+                .collect(),
+            initial: to_blocks(trace.initial),
+            rounds: trace
+                .rounds
+                .into_iter()
+                .map(|round| MinimizeRound {
+                    partition: to_blocks(round.partition),
+                    splitter: round.splitter.into(),
+                    splits: round
+                        .splits
+                        .into_iter()
+                        .map(|split| MinimizeSplit {
+                            alphabet: split.alphabet,
+                            block: split.block.into(),
+                            inside: split.inside.into(),
+                            outside: split.outside.into(),
+                        })
+                        .collect(),
+                })
+                .collect(),
+            sink: trace.sink,
         }
     }
 }
@@ -1,55 +1,403 @@
 use std::{
     fs::File,
     io::{BufRead, BufReader},
-    path::PathBuf,
+    path::{Path, PathBuf},
 };
 
-use clap::Parser;
-use regend::{dfa::Dfa, nfa::GlobalEnv, parser};
+use clap::{Parser, Subcommand, ValueEnum};
+use regend::{
+    dfa::{Dfa, RunResult},
+    equiv::Equivalence,
+    nfa::{simulator::NfaSimulator, Budget, GlobalEnv, Limits, Nfa},
+    parser,
+    product::BoolOp,
+    regexpr::{get_alphabets_with, RegExpr},
+    render::{self, Format, Graph},
+    Error,
+};
 
 #[derive(Debug, Parser)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// 正規表現
-    reg: String,
+    #[arg(required = true)]
+    reg: Option<String>,
 
     #[clap(short = 't')]
     test: Option<PathBuf>,
 
+    /// DFAを作らず、-tの文字列をNFAのまま読み込ませて状態の集合の移り変わりを表示する
+    #[clap(short = 'n', long)]
+    nfa: bool,
+
     #[clap(short = 'k')]
     table: bool,
+
+    /// 最小化したDFAも表示する
+    #[clap(short = 'm')]
+    minimize: bool,
+
+    /// 最小化の過程も表示する (-mを含む)
+    #[clap(short = 's')]
+    steps: bool,
+
+    /// 出力形式。指定すると--graphで選んだ図だけを出力する。指定しなければテキストで出力する
+    #[arg(long, value_enum)]
+    format: Option<Format>,
+
+    /// --formatを指定したときに出力する図
+    #[arg(long, value_enum, default_value_t = Graph::Dfa)]
+    graph: Graph,
+
+    /// 考えている文字の集合。正規表現に現れる文字は常に含まれる。a-zのように範囲でも書ける。
+    /// 否定の文字クラス[^...]や.はこの集合の上で考える
+    #[arg(short = 'a', long, default_value = "")]
+    alphabet: String,
+
+    #[command(flatten)]
+    limits: LimitArgs,
+}
+
+/// 変換の上限。共通部分や補集合のために途中で作るDFAも合わせて数える
+#[derive(Debug, clap::Args)]
+struct LimitArgs {
+    /// DFAの状態数の上限。超えたら変換を中断する
+    #[arg(long)]
+    max_states: Option<usize>,
+
+    /// DFAを作るときに遷移を計算する回数の上限。超えたら変換を中断する
+    #[arg(long)]
+    max_steps: Option<usize>,
+}
+
+impl From<&LimitArgs> for Limits {
+    fn from(value: &LimitArgs) -> Self {
+        Limits {
+            max_states: value.max_states,
+            max_steps: value.max_steps,
+        }
+    }
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// 2つの正規表現が同じ言語を表すか調べる。異なる場合は終了コード1で終了する
+    Equiv {
+        /// 正規表現
+        left: String,
+        /// 正規表現
+        right: String,
+        /// 考えている文字の集合。正規表現に現れる文字は常に含まれる
+        #[arg(short = 'a', long, default_value = "")]
+        alphabet: String,
+        #[command(flatten)]
+        limits: LimitArgs,
+    },
+    /// 2つの正規表現から作ったDFAの直積オートマトンを表示する
+    Product {
+        #[arg(value_enum)]
+        op: Op,
+        /// 正規表現
+        left: String,
+        /// 正規表現
+        right: String,
+        /// 考えている文字の集合。正規表現に現れる文字は常に含まれる
+        #[arg(short = 'a', long, default_value = "")]
+        alphabet: String,
+        #[command(flatten)]
+        limits: LimitArgs,
+    },
+    /// 正規表現から作ったDFAの補集合を表示する
+    Complement {
+        /// 正規表現
+        reg: String,
+        /// 補集合をとる文字の集合。正規表現に現れる文字は常に含まれる。a-zのように範囲でも書ける
+        #[arg(short = 'a', long, default_value = "")]
+        alphabet: String,
+        #[command(flatten)]
+        limits: LimitArgs,
+    },
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Op {
+    /// 共通部分
+    Intersection,
+    /// 和集合
+    Union,
+    /// 差集合
+    Difference,
+    /// 対称差
+    SymmetricDifference,
+}
+
+impl From<Op> for BoolOp {
+    fn from(value: Op) -> Self {
+        match value {
+            Op::Intersection => BoolOp::Intersection,
+            Op::Union => BoolOp::Union,
+            Op::Difference => BoolOp::Difference,
+            Op::SymmetricDifference => BoolOp::SymmetricDifference,
+        }
+    }
 }
 
 fn main() {
     let args = Args::parse();
 
-    let reg = match parser::parse_expr_until_end(&args.reg) {
-        Ok(reg) => reg.1,
-        Err(e) => panic!("正規表現のパースエラー: {}", e),
+    match args.command {
+        Some(Command::Equiv {
+            left,
+            right,
+            alphabet,
+            limits,
+        }) => equiv(&left, &right, &parse_alphabet(&alphabet), (&limits).into()),
+        Some(Command::Product {
+            op,
+            left,
+            right,
+            alphabet,
+            limits,
+        }) => product(
+            op.into(),
+            &left,
+            &right,
+            &parse_alphabet(&alphabet),
+            (&limits).into(),
+        ),
+        Some(Command::Complement {
+            reg,
+            alphabet,
+            limits,
+        }) => complement(&reg, &parse_alphabet(&alphabet), (&limits).into()),
+        None => {
+            let reg = parse(args.reg.as_deref().unwrap());
+            convert(reg, &args);
+        }
+    }
+}
+
+fn parse(reg: &str) -> RegExpr {
+    parser::parse(reg).unwrap_or_else(|e| exit_with(e, reg))
+}
+
+fn parse_alphabet(alphabet: &str) -> Vec<char> {
+    parser::parse_alphabet(alphabet).unwrap_or_else(|e| exit_with(e, alphabet))
+}
+
+/// `source`を扱っていて起きたエラー`e`を表示して終了する
+fn exit_with(e: Error, source: &str) -> ! {
+    match e {
+        Error::Parse(d) => eprintln!("{}", d.render(source)),
+        e => eprintln!("{e}"),
+    }
+    std::process::exit(1);
+}
+
+fn equiv(left: &str, right: &str, alphabet: &[char], limits: Limits) {
+    let source = left;
+    let left = parse(left);
+    let right = parse(right);
+    println!("left:  {left:#}");
+    println!("right: {right:#}");
+    let equivalence = left
+        .equivalent_with_limits(&right, alphabet, limits)
+        .unwrap_or_else(|e| exit_with(e, source));
+    match equivalence {
+        Equivalence::Equivalent => println!("Equivalent"),
+        Equivalence::Different {
+            witness,
+            accepted_by_left,
+        } => {
+            let side = if accepted_by_left { "left" } else { "right" };
+            println!("Different: \"{witness}\" is accepted only by {side}");
+            std::process::exit(1);
+        }
+    }
+}
+
+fn product(op: BoolOp, left: &str, right: &str, alphabet: &[char], limits: Limits) {
+    let source = left;
+    let left = parse(left);
+    let right = parse(right);
+    let alphabets = get_alphabets_with(&[&left, &right], alphabet);
+    // 両辺のDFAと直積を合わせて上限と比べる
+    let mut budget = Budget::new(limits);
+    let mut to_dfa = |r: &RegExpr| {
+        r.to_dfa_with_budget(&alphabets, &mut budget)
+            .unwrap_or_else(|e| exit_with(e, source))
     };
+    let left_dfa = to_dfa(&left);
+    let right_dfa = to_dfa(&right);
+    let product = left_dfa
+        .product_with_budget(&right_dfa, op, &mut budget)
+        .unwrap_or_else(|e| exit_with(e, source));
+    println!("left: {left:#}");
+    println!("{left_dfa}");
+    println!();
+    println!("right: {right:#}");
+    println!("{right_dfa}");
+    println!();
+    println!("{product}");
+}
 
-    let mut env = GlobalEnv::default();
-    let nfa = reg.to_nfa(&mut env);
-    println!("regexpr: {reg}");
-    println!("{nfa}");
+fn complement(reg: &str, alphabet: &[char], limits: Limits) {
+    let source = reg;
+    let reg = parse(reg);
+    let mut budget = Budget::new(limits);
+    let dfa = reg
+        .to_dfa_with_budget(&get_alphabets_with(&[&reg], alphabet), &mut budget)
+        .unwrap_or_else(|e| exit_with(e, source));
+    let complement = dfa
+        .complement_with_budget(alphabet, &mut budget)
+        .unwrap_or_else(|e| exit_with(e, source));
+    println!("regexpr: {reg:#}");
+    println!("{dfa}");
+    println!();
+    println!("{complement}");
+}
+
+fn render(reg: &RegExpr, nfa: &Nfa, dfa: &Dfa, format: Format, graph: Graph) {
+    match render::render(reg, nfa, dfa, graph, format) {
+        Ok(s) => print!("{s}"),
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+    }
+}
+
+fn convert(reg: RegExpr, args: &Args) {
+    let alphabets = get_alphabets_with(&[&reg], &parse_alphabet(&args.alphabet));
+    // NFAの中で作るDFAと部分集合構成法を合わせて上限と比べる
+    let mut budget = Budget::new((&args.limits).into());
+    let source = args.reg.as_deref().unwrap();
+    let mut env = GlobalEnv::new(&alphabets);
+    let nfa = reg
+        .to_nfa_with_budget(&mut env, &mut budget)
+        .unwrap_or_else(|e| exit_with(e, source));
+
+    if args.nfa {
+        simulate(&reg, &nfa, args);
+        return;
+    }
 
-    let dfa = nfa.to_dfa(&reg.get_alphabets());
+    let (dfa, states) = nfa
+        .subset_construction_with_budget(&alphabets, &mut budget)
+        .unwrap_or_else(|e| exit_with(e, source));
+
+    if let Some(format) = args.format {
+        render(&reg, &nfa, &dfa, format, args.graph);
+        return;
+    }
+
+    println!("regexpr: {reg:#}");
+    // 展開した正規表現も上限の範囲内でだけ作って表示する
+    if budget.add_steps(reg.desugared_size(), 0).is_ok() {
+        let desugared = reg.desugar();
+        if desugared.to_string() != reg.to_string() {
+            println!("desugared: {desugared:#}");
+        }
+    }
+    println!("{nfa}");
+    println!();
+    println!("{states}");
     println!();
     println!("{dfa}");
 
-    if let Some(test_file) = args.test {
+    if args.minimize || args.steps {
+        println!();
+        let (minimized, trace) = dfa.minimize_with_trace();
+        if args.steps {
+            println!("MINIMIZATION STEPS");
+            println!("{trace}");
+        }
+        println!("{minimized}");
+    }
+
+    if let Some(test_file) = &args.test {
         println!();
         println!("Running tests...");
-        let f =
-            File::open(&test_file).expect(&format!("ファイル {} が開けない", test_file.display()));
-        let reader = BufReader::new(f);
-        for line in reader.lines() {
-            dfa.run(&line.expect(&format!("ファイル {} を読み込めない", test_file.display())));
+        let dense = dfa.compile();
+        for line in read_tests(test_file) {
+            print_run(&dfa, &line, &dense.run(&line));
         }
     }
 
     if args.table {
         println!();
         println!("TABLE");
-        Dfa::print_table(&dfa.to_table());
+        print!("{}", Dfa::format_table(&dfa.to_table()));
+    }
+}
+
+/// テストする文字列のファイルを1行ずつ読み込む
+fn read_tests(test_file: &Path) -> Vec<String> {
+    let f = File::open(test_file)
+        .unwrap_or_else(|_| panic!("ファイル {} が開けない", test_file.display()));
+    BufReader::new(f)
+        .lines()
+        .map(|line| {
+            line.unwrap_or_else(|_| panic!("ファイル {} を読み込めない", test_file.display()))
+        })
+        .collect()
+}
+
+fn print_run(dfa: &Dfa, input: &str, result: &RunResult) {
+    const GREEN: &str = "\x1b[32m";
+    const RED: &str = "\x1b[31m";
+    const RESET: &str = "\x1b[0m";
+
+    let mut trace = dfa.start.to_string();
+    for (_, _, to) in &result.path {
+        trace.push_str(&format!("->{to}"));
+    }
+    print!("\"{input}\"\t{trace}");
+    if let Some((state, c)) = result.stuck_at {
+        print!(" (状態{state}から'{c}'での遷移規則がない)");
+    }
+    if result.accepted {
+        println!("\t{GREEN}Accepted{RESET}");
+    } else {
+        println!("\t{RED}Rejected{RESET}");
+    }
+}
+
+/// DFAを作らずに、-tの文字列をNFAのまま読み込ませる
+fn simulate(reg: &RegExpr, nfa: &Nfa, args: &Args) {
+    println!("regexpr: {reg:#}");
+    println!("{nfa}");
+
+    if let Some(test_file) = &args.test {
+        println!();
+        println!("Running tests on NFA...");
+        let mut simulator = nfa.simulator();
+        for line in read_tests(test_file) {
+            print_nfa_run(&mut simulator, nfa, &line);
+        }
+    }
+}
+
+fn print_nfa_run(simulator: &mut NfaSimulator, nfa: &Nfa, input: &str) {
+    const GREEN: &str = "\x1b[32m";
+    const RED: &str = "\x1b[31m";
+    const RESET: &str = "\x1b[0m";
+
+    let trace = simulator.run_trace(input);
+    // 途中で空になった場合は最後の集合が空なので受理しない
+    let accepted = trace.last().unwrap().contains(&nfa.finish);
+    let trace = trace
+        .iter()
+        .map(|states| format!("{states:?}"))
+        .collect::<Vec<_>>()
+        .join("->");
+    print!("\"{input}\"\t{trace}");
+    if accepted {
+        println!("\t{GREEN}Accepted{RESET}");
+    } else {
+        println!("\t{RED}Rejected{RESET}");
     }
 }
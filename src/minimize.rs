@@ -0,0 +1,397 @@
+use std::{
+    collections::{hash_map::Entry, BTreeMap, BTreeSet, HashMap, VecDeque},
+    fmt::Display,
+};
+
+use itertools::Itertools;
+
+use crate::{
+    dfa::{Dfa, Rule, State},
+    error::Error,
+    nfa::Budget,
+};
+
+/// DFAの最小化の結果
+#[derive(Debug, PartialEq, Eq)]
+pub struct Minimized {
+    /// 最小DFA
+    pub dfa: Dfa,
+    /// 元のDFAの状態 => 最小DFAの状態(同値類)。開始状態から到達できない状態は含まない
+    pub classes: BTreeMap<State, State>,
+}
+
+impl Dfa {
+    /// Hopcroftの分割統治アルゴリズムでDFAを最小化する
+    ///
+    /// 遷移規則が欠けている場合は、暗黙の死に状態へ遷移するものとして扱う。
+    pub fn minimize(&self) -> Minimized {
+        let indexed = Indexed::new(self);
+        let block_of = hopcroft(&indexed, None);
+        indexed.build(&block_of)
+    }
+
+    /// `minimize`と同じだが、遷移規則の数だけ遷移を計算したものとして`budget`で数え、
+    /// 上限を超えるなら最小化せずに中断する。最小化で状態は増えないので、状態は数えない
+    pub fn minimize_with_budget(&self, budget: &mut Budget) -> Result<Minimized, Error> {
+        budget.add_steps(self.rules.len(), 0)?;
+        Ok(self.minimize())
+    }
+
+    /// `minimize`と同じだが、分割の精密化の過程も返す
+    pub fn minimize_with_trace(&self) -> (Minimized, Trace) {
+        let indexed = Indexed::new(self);
+        let mut rounds = vec![];
+        let block_of = hopcroft(&indexed, Some(&mut rounds));
+        let trace = Trace {
+            initial: indexed.to_states(&initial_partition(&indexed)),
+            rounds,
+            sink: indexed.sink.map(|sink| indexed.states[sink]),
+        };
+        (indexed.build(&block_of), trace)
+    }
+
+    /// Mooreのアルゴリズムで最小化する
+    ///
+    /// `minimize`の検算用の素朴な実装。状態の番号付けは`minimize`と同じになるので、
+    /// 結果を`==`で直接比較できる。
+    pub fn minimize_moore(&self) -> Minimized {
+        let indexed = Indexed::new(self);
+        let block_of = moore(&indexed);
+        indexed.build(&block_of)
+    }
+}
+
+/// 最小化の過程
+#[derive(Debug, PartialEq, Eq)]
+pub struct Trace {
+    /// 受理状態とそれ以外に分けた初期分割
+    pub initial: Vec<BTreeSet<State>>,
+    pub rounds: Vec<Round>,
+    /// 遷移規則が欠けていたために補った死に状態
+    pub sink: Option<State>,
+}
+
+/// 待ち行列から取り出した1つのブロック(splitter)で、分割を精密化する1ラウンド
+#[derive(Debug, PartialEq, Eq)]
+pub struct Round {
+    /// このラウンドを始める前の分割
+    pub partition: Vec<BTreeSet<State>>,
+    pub splitter: BTreeSet<State>,
+    /// このラウンドで起きた分割。空ならどのブロックも分割されなかった
+    pub splits: Vec<Split>,
+}
+
+/// ブロック`block`が、文字`alphabet`でsplitterに遷移するかどうかで2つに分かれたこと
+#[derive(Debug, PartialEq, Eq)]
+pub struct Split {
+    pub alphabet: char,
+    pub block: BTreeSet<State>,
+    /// `alphabet`でsplitterに遷移する状態
+    pub inside: BTreeSet<State>,
+    /// `alphabet`でsplitterに遷移しない状態
+    pub outside: BTreeSet<State>,
+}
+
+/// 開始状態から到達可能な部分だけを取り出し、状態と文字を添字に振り直したDFA
+struct Indexed {
+    /// 添字 => 元の状態。死に状態を補った場合、最後の要素がそれになる
+    states: Vec<State>,
+    alphabets: Vec<char>,
+    /// `delta[p][c]`は状態`p`から文字`c`での遷移先
+    delta: Vec<Vec<usize>>,
+    finish: Vec<bool>,
+    /// 補った死に状態の添字
+    sink: Option<usize>,
+}
+
+impl Indexed {
+    fn new(dfa: &Dfa) -> Self {
+        let alphabets: Vec<char> = dfa.alphabets().into_iter().collect();
+
+        let mut index = HashMap::new();
+        let mut states = vec![dfa.start];
+        index.insert(dfa.start, 0);
+        let mut next: Vec<Vec<Option<State>>> = vec![];
+        let mut i = 0;
+        while i < states.len() {
+            let row: Vec<_> = alphabets.iter().map(|c| dfa.next(states[i], *c)).collect();
+            for to in row.iter().flatten() {
+                if let Entry::Vacant(e) = index.entry(*to) {
+                    e.insert(states.len());
+                    states.push(*to);
+                }
+            }
+            next.push(row);
+            i += 1;
+        }
+
+        let sink = if next.iter().flatten().any(|to| to.is_none()) {
+            Some(states.len())
+        } else {
+            None
+        };
+        let mut delta: Vec<Vec<usize>> = next
+            .into_iter()
+            .map(|row| {
+                row.into_iter()
+                    .map(|to| to.map_or_else(|| sink.unwrap(), |to| index[&to]))
+                    .collect()
+            })
+            .collect();
+        let mut finish: Vec<bool> = states
+            .iter()
+            .map(|s| dfa.finish_states.contains(s))
+            .collect();
+        if let Some(sink) = sink {
+            states.push(states.iter().max().unwrap() + 1);
+            delta.push(vec![sink; alphabets.len()]);
+            finish.push(false);
+        }
+
+        Self {
+            states,
+            alphabets,
+            delta,
+            finish,
+            sink,
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.states.len()
+    }
+
+    fn to_set(&self, block: &[usize]) -> BTreeSet<State> {
+        block.iter().map(|p| self.states[*p]).collect()
+    }
+
+    fn to_states(&self, blocks: &[Vec<usize>]) -> Vec<BTreeSet<State>> {
+        blocks.iter().map(|block| self.to_set(block)).collect()
+    }
+
+    /// `delta`の逆。`inverse[c][q]`は文字`c`で状態`q`に遷移する状態の一覧
+    fn inverse(&self) -> Vec<Vec<Vec<usize>>> {
+        let mut inverse = vec![vec![vec![]; self.len()]; self.alphabets.len()];
+        for (p, row) in self.delta.iter().enumerate() {
+            for (c, q) in row.iter().enumerate() {
+                inverse[c][*q].push(p);
+            }
+        }
+        inverse
+    }
+
+    /// 各状態が属するブロックの番号から最小DFAを組み立てる。
+    /// 最小DFAの状態には、開始状態から幅優先で辿った順に1から番号を振る。
+    fn build(&self, block_of: &[usize]) -> Minimized {
+        let mut number = HashMap::new();
+        let mut representative = vec![];
+        let mut queue = VecDeque::new();
+        number.insert(block_of[0], 1);
+        representative.push(0);
+        queue.push_back(0);
+        while let Some(p) = queue.pop_front() {
+            for q in &self.delta[p] {
+                if let Entry::Vacant(e) = number.entry(block_of[*q]) {
+                    e.insert(representative.len() as State + 1);
+                    representative.push(*q);
+                    queue.push_back(*q);
+                }
+            }
+        }
+
+        let mut rules = BTreeSet::new();
+        let mut finish_states = BTreeSet::new();
+        for p in representative {
+            let from = number[&block_of[p]];
+            if self.finish[p] {
+                finish_states.insert(from);
+            }
+            for (c, q) in self.delta[p].iter().enumerate() {
+                rules.insert(Rule {
+                    from,
+                    alphabet: self.alphabets[c],
+                    to: number[&block_of[*q]],
+                });
+            }
+        }
+
+        let classes = (0..self.len())
+            .filter(|p| Some(*p) != self.sink)
+            .map(|p| (self.states[p], number[&block_of[p]]))
+            .collect();
+
+        Minimized {
+            dfa: Dfa {
+                start: 1,
+                finish_states,
+                rules,
+            },
+            classes,
+        }
+    }
+}
+
+/// 受理状態とそれ以外に分けた初期分割
+fn initial_partition(dfa: &Indexed) -> Vec<Vec<usize>> {
+    let (finish, other): (Vec<usize>, Vec<usize>) = (0..dfa.len()).partition(|p| dfa.finish[*p]);
+    [finish, other]
+        .into_iter()
+        .filter(|block| !block.is_empty())
+        .collect()
+}
+
+fn hopcroft(dfa: &Indexed, mut trace: Option<&mut Vec<Round>>) -> Vec<usize> {
+    let inverse = dfa.inverse();
+    let mut blocks = initial_partition(dfa);
+    let mut block_of = vec![0; dfa.len()];
+    for (b, block) in blocks.iter().enumerate() {
+        for p in block {
+            block_of[*p] = b;
+        }
+    }
+
+    // 分割に使うブロックの待ち行列。初期分割では小さい方だけで十分
+    let mut waiting = VecDeque::new();
+    let mut in_waiting = vec![false; blocks.len()];
+    if blocks.len() == 2 {
+        let smaller = if blocks[0].len() <= blocks[1].len() {
+            0
+        } else {
+            1
+        };
+        waiting.push_back(smaller);
+        in_waiting[smaller] = true;
+    }
+
+    while let Some(a) = waiting.pop_front() {
+        in_waiting[a] = false;
+        let splitter = blocks[a].clone();
+        let mut round = trace.as_ref().map(|_| Round {
+            partition: dfa.to_states(&blocks),
+            splitter: dfa.to_set(&splitter),
+            splits: vec![],
+        });
+        for (c, inverse_c) in inverse.iter().enumerate() {
+            // 文字cでsplitterに遷移する状態を、属するブロックごとに集める
+            let mut hit: BTreeMap<usize, BTreeSet<usize>> = BTreeMap::new();
+            for q in &splitter {
+                for p in &inverse_c[*q] {
+                    hit.entry(block_of[*p]).or_default().insert(*p);
+                }
+            }
+
+            for (y, inside) in hit {
+                if inside.len() == blocks[y].len() {
+                    continue;
+                }
+                let (inside, outside): (Vec<usize>, Vec<usize>) =
+                    blocks[y].iter().copied().partition(|p| inside.contains(p));
+                if let Some(round) = &mut round {
+                    round.splits.push(Split {
+                        alphabet: dfa.alphabets[c],
+                        block: dfa.to_set(&blocks[y]),
+                        inside: dfa.to_set(&inside),
+                        outside: dfa.to_set(&outside),
+                    });
+                }
+                let z = blocks.len();
+                for p in &outside {
+                    block_of[*p] = z;
+                }
+                blocks[y] = inside;
+                blocks.push(outside);
+                in_waiting.push(false);
+
+                let next = if in_waiting[y] || blocks[z].len() < blocks[y].len() {
+                    z
+                } else {
+                    y
+                };
+                waiting.push_back(next);
+                in_waiting[next] = true;
+            }
+        }
+        if let (Some(trace), Some(round)) = (&mut trace, round) {
+            trace.push(round);
+        }
+    }
+
+    block_of
+}
+
+fn moore(dfa: &Indexed) -> Vec<usize> {
+    let mut block_of = vec![0; dfa.len()];
+    for (b, block) in initial_partition(dfa).iter().enumerate() {
+        for p in block {
+            block_of[*p] = b;
+        }
+    }
+    let mut count = block_of.iter().collect::<BTreeSet<_>>().len();
+
+    loop {
+        // 現在のブロックと、各文字での遷移先のブロックの組が同じ状態どうしを同じブロックにする
+        let mut signatures = HashMap::new();
+        let next: Vec<usize> = (0..dfa.len())
+            .map(|p| {
+                let signature: Vec<usize> = std::iter::once(block_of[p])
+                    .chain(dfa.delta[p].iter().map(|q| block_of[*q]))
+                    .collect();
+                let n = signatures.len();
+                *signatures.entry(signature).or_insert(n)
+            })
+            .collect();
+        block_of = next;
+        if signatures.len() == count {
+            return block_of;
+        }
+        count = signatures.len();
+    }
+}
+
+impl Display for Minimized {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("DFA states <=> minimal DFA state\n")?;
+        let mut groups: BTreeMap<State, BTreeSet<State>> = BTreeMap::new();
+        for (state, class) in &self.classes {
+            groups.entry(*class).or_default().insert(*state);
+        }
+        for (class, states) in &groups {
+            f.write_fmt(format_args!("{:?}\t{}\n", states, class))?;
+        }
+        f.write_fmt(format_args!("\n{}", self.dfa))
+    }
+}
+
+fn fmt_partition(partition: &[BTreeSet<State>]) -> String {
+    partition
+        .iter()
+        .map(|block| format!("{:?}", block))
+        .join(" ")
+}
+
+impl Display for Trace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(sink) = self.sink {
+            f.write_fmt(format_args!(
+                "sink: {} (遷移先がない場合の死に状態)\n",
+                sink
+            ))?;
+        }
+        f.write_fmt(format_args!("initial: {}\n", fmt_partition(&self.initial)))?;
+        for (i, round) in self.rounds.iter().enumerate() {
+            f.write_fmt(format_args!(
+                "round {}: partition {}, splitter {:?}\n",
+                i + 1,
+                fmt_partition(&round.partition),
+                round.splitter
+            ))?;
+            for split in &round.splits {
+                f.write_fmt(format_args!(
+                    "  {:?} -> {:?} {:?} ('{}'でsplitterに遷移する/しない)\n",
+                    split.block, split.inside, split.outside, split.alphabet
+                ))?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,48 +1,84 @@
 use std::{
+    cell::OnceCell,
     collections::{BTreeSet, HashMap, VecDeque},
     fmt::Display,
 };
 
-use crate::dfa::{self, Dfa};
+use crate::{
+    dfa::{self, Dfa},
+    error::Error,
+    parser,
+};
+
+pub mod adjacency;
+pub mod simulator;
+
+use adjacency::{Adjacency, Closures};
 
 pub type State = i32;
 
+#[derive(Default)]
 pub struct GlobalEnv {
     pub current: State,
+    /// 補集合などで使う、考えている文字の集合全体
+    pub alphabets: Vec<char>,
 }
 
-impl Default for GlobalEnv {
-    fn default() -> Self {
-        Self { current: 0 }
+impl GlobalEnv {
+    pub fn new(alphabets: &[char]) -> Self {
+        Self {
+            current: 0,
+            alphabets: alphabets.to_vec(),
+        }
     }
-}
 
-impl GlobalEnv {
     pub fn new_state(&mut self) -> State {
         self.current += 1;
         self.current
     }
 }
 
+/// NFAの遷移規則のラベル
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Label {
+    /// 文字を読まずに遷移するε遷移
+    Epsilon,
+    /// 文字を1つ読んで遷移する
+    Symbol(char),
+}
+
 #[derive(Debug)]
 pub struct Rule {
     pub from: State,
     pub to: State,
-    pub alphabet: char,
+    pub label: Label,
 }
 
 #[derive(Debug)]
 pub struct Nfa {
     pub start: State,
     pub finish: State,
-    pub rules: Vec<Rule>,
+    /// 作った後は書き換えないので、`index`と食い違うことはない
+    rules: Vec<Rule>,
+    /// 遷移規則の索引。初めて遷移を引くときに作る
+    index: OnceCell<Adjacency>,
+}
+
+/// ε遷移は`ε`、文字の`ε`は`\ε`のように正規表現と同じ書き方で表示する
+impl Display for Label {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Label::Epsilon => f.write_str("ε"),
+            Label::Symbol(c) => f.write_str(&parser::escape(*c)),
+        }
+    }
 }
 
 impl Display for Rule {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.write_fmt(format_args!(
             "{} -- '{}' --> {}",
-            self.from, self.alphabet, self.to
+            self.from, self.label, self.to
         ))
     }
 }
@@ -60,17 +96,96 @@ impl Display for Nfa {
 }
 
 impl Nfa {
-    pub fn edge(&self, s: State, c: char) -> BTreeSet<State> {
-        let mut ret = BTreeSet::new();
-        for t in self
-            .rules
-            .iter()
-            .filter(|r| r.from == s && r.alphabet == c)
-            .map(|r| r.to)
-        {
-            ret.insert(t);
+    pub fn new(start: State, finish: State, rules: Vec<Rule>) -> Self {
+        Self {
+            start,
+            finish,
+            rules,
+            index: OnceCell::new(),
+        }
+    }
+
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+
+    /// 遷移規則を取り出す。Thompsonの構成で、部分のNFAの遷移規則を組み合わせるのに使う
+    pub(crate) fn take_rules(&mut self) -> Vec<Rule> {
+        self.index = OnceCell::new();
+        std::mem::take(&mut self.rules)
+    }
+
+    /// 遷移規則の索引。最初に呼んだときに作り、以降は同じものを返す
+    pub fn adjacency(&self) -> &Adjacency {
+        self.index.get_or_init(|| Adjacency::new(self))
+    }
+
+    /// DFAと同じ言語を受理するNFAを作る。受理状態に到達できない状態は取り除く
+    pub fn from_dfa(dfa: &Dfa, env: &mut GlobalEnv) -> Nfa {
+        let mut live = dfa.finish_states.clone();
+        loop {
+            let before = live.len();
+            for rule in &dfa.rules {
+                if live.contains(&rule.to) {
+                    live.insert(rule.from);
+                }
+            }
+            if live.len() == before {
+                break;
+            }
+        }
+
+        let start = env.new_state();
+        let mut states = HashMap::new();
+        states.insert(dfa.start, start);
+        for state in &live {
+            states.entry(*state).or_insert_with(|| env.new_state());
+        }
+        let finish = env.new_state();
+
+        let mut rules = vec![];
+        for rule in &dfa.rules {
+            if live.contains(&rule.from) && live.contains(&rule.to) {
+                rules.push(Rule {
+                    from: states[&rule.from],
+                    to: states[&rule.to],
+                    label: Label::Symbol(rule.alphabet),
+                });
+            }
+        }
+        for state in &dfa.finish_states {
+            rules.push(Rule {
+                from: states[state],
+                to: finish,
+                label: Label::Epsilon,
+            });
+        }
+
+        Nfa::new(start, finish, rules)
+    }
+
+    /// 開始状態、受理状態と、遷移規則に現れるすべての状態
+    pub fn states(&self) -> BTreeSet<State> {
+        let mut states = BTreeSet::new();
+        states.insert(self.start);
+        states.insert(self.finish);
+        for rule in &self.rules {
+            states.insert(rule.from);
+            states.insert(rule.to);
+        }
+        states
+    }
+
+    /// 状態`s`から`label`で遷移できる状態。遷移規則の索引で引く
+    pub fn edge(&self, s: State, label: Label) -> BTreeSet<State> {
+        let adjacency = self.adjacency();
+        match adjacency.position(s) {
+            Some(i) => adjacency
+                .targets(i, label)
+                .map(|t| adjacency.state(t))
+                .collect(),
+            None => BTreeSet::new(),
         }
-        ret
     }
 
     pub fn closure_(&self, state: State) -> BTreeSet<State> {
@@ -79,17 +194,20 @@ impl Nfa {
         self.closure(&set)
     }
 
+    /// ε遷移で到達できる状態を、遷移規則の索引を引きながらワークリストで辿って集める
     pub fn closure(&self, states: &BTreeSet<State>) -> BTreeSet<State> {
+        let adjacency = self.adjacency();
         let mut t = states.clone();
-        loop {
-            let mut t_dash = t.clone();
-            for s in &t {
-                t_dash.extend(self.edge(*s, 'ε'));
-            }
-            if t.len() == t_dash.len() {
-                break;
+        let mut worklist: Vec<usize> = states
+            .iter()
+            .filter_map(|s| adjacency.position(*s))
+            .collect();
+        while let Some(i) = worklist.pop() {
+            for j in adjacency.targets(i, Label::Epsilon) {
+                if t.insert(adjacency.state(j)) {
+                    worklist.push(j);
+                }
             }
-            t = t_dash;
         }
         t
     }
@@ -97,65 +215,190 @@ impl Nfa {
     pub fn dfa_edge(&self, states: &BTreeSet<State>, c: char) -> BTreeSet<State> {
         let mut e = BTreeSet::new();
         for s in states {
-            e.extend(self.edge(*s, c));
+            e.extend(self.edge(*s, Label::Symbol(c)));
         }
         self.closure(&e)
     }
 
+    /// DFAに変換せず、取りうる状態の集合を持ち回って文字列`input`を読み込ませる。
+    /// 結果は`NfaSimulator::run_trace`と同じ
+    ///
+    /// 遷移規則の索引は使い回すが、ε閉包のメモは呼ぶたびに作り直すので、
+    /// 多くの文字列を読み込ませるときは`simulator`を使う。
+    pub fn run_trace(&self, input: &str) -> Vec<BTreeSet<State>> {
+        self.simulator().run_trace(input)
+    }
+
+    /// DFAに変換せずに、文字列`input`を受理するかどうか調べる
+    ///
+    /// 遷移規則の索引は使い回すが、ε閉包のメモは呼ぶたびに作り直すので、
+    /// 多くの文字列を読み込ませるときは`simulator`を使う。
+    pub fn accepts(&self, input: &str) -> bool {
+        self.simulator().accepts(input)
+    }
+
     pub fn to_dfa(&self, alphabets: &[char]) -> Dfa {
-        let mut states = DfaStateProvider::default();
+        self.subset_construction(alphabets).0
+    }
+
+    /// 部分集合構成法でDFAに変換し、NFAの状態の集合とDFAの状態の対応も返す
+    pub fn subset_construction(&self, alphabets: &[char]) -> (Dfa, DfaStateProvider) {
+        match self.subset_construction_with_budget(alphabets, &mut Budget::default()) {
+            Ok(v) => v,
+            Err(_) => unreachable!("上限がなければ部分集合構成法は中断しない"),
+        }
+    }
+
+    /// `subset_construction`と同じだが、DFAの状態数や遷移の計算回数が`limits`を超えたら中断して
+    /// `Error::StateExplosion`を返す
+    pub fn subset_construction_with_limits(
+        &self,
+        alphabets: &[char],
+        limits: Limits,
+    ) -> Result<(Dfa, DfaStateProvider), Error> {
+        self.subset_construction_with_budget(alphabets, &mut Budget::new(limits))
+    }
+
+    /// `subset_construction`と同じだが、作ったDFAの状態と遷移の計算を`budget`で数え、
+    /// 上限を超えたら中断する
+    ///
+    /// 遷移は`Adjacency`で引き、NFAの状態の集合は添字の列のまま扱う。
+    /// 初めて現れた集合だけをキューに積むので、各集合から出る遷移は一度ずつしか計算しない。
+    pub fn subset_construction_with_budget(
+        &self,
+        alphabets: &[char],
+        budget: &mut Budget,
+    ) -> Result<(Dfa, DfaStateProvider), Error> {
+        let adjacency = self.adjacency();
+        let mut closures = Closures::new(adjacency);
+        let mut provider = DfaStateProvider::default();
+        // 添字の列 => DFAの状態
+        let mut states: HashMap<Vec<usize>, dfa::State> = HashMap::new();
         let mut queue = VecDeque::new();
         let mut rules = BTreeSet::new();
 
-        let start_closure = self.closure_(self.start);
-
+        budget.add_state(0)?;
+        let start_closure = closures.closure(&[adjacency.index(self.start)]);
+        let start = provider.get_dfa_state(adjacency.to_states(&start_closure));
+        states.insert(start_closure.clone(), start);
         queue.push_back(start_closure);
 
         while let Some(nfa_states) = queue.pop_front() {
-            let from = states.to_dfa_state(nfa_states.clone());
+            let from = states[&nfa_states];
             for c in alphabets {
-                let next_nfa_states = self.dfa_edge(&nfa_states, *c);
-                let to = states.to_dfa_state(next_nfa_states.clone());
-                let rule = dfa::Rule {
+                // 取り出した集合はまだ処理し終えていないので、未処理の数に含める
+                budget.add_steps(1, queue.len() + 1)?;
+                let next_nfa_states = closures.step(&nfa_states, *c);
+                let to = match states.get(&next_nfa_states) {
+                    Some(to) => *to,
+                    None => {
+                        budget.add_state(queue.len() + 1)?;
+                        let to = provider.get_dfa_state(adjacency.to_states(&next_nfa_states));
+                        states.insert(next_nfa_states.clone(), to);
+                        queue.push_back(next_nfa_states);
+                        to
+                    }
+                };
+                rules.insert(dfa::Rule {
                     from,
                     to,
                     alphabet: *c,
-                };
-                if rules.contains(&rule) {
-                    continue;
-                }
-                rules.insert(rule);
-                queue.push_back(next_nfa_states);
+                });
             }
         }
 
-        println!();
-        println!("{states}");
-
-        Dfa {
-            start: states.to_dfa_state(self.closure_(self.start)),
-            finish_states: states.get_dfa_finishes(self.finish),
+        let dfa = Dfa {
+            start,
+            finish_states: provider.get_dfa_finishes(self.finish),
             rules,
-        }
+        };
+        Ok((dfa, provider))
     }
 }
 
-struct DfaStateProvider {
-    states: HashMap<BTreeSet<State>, dfa::State>,
-    current: dfa::State,
+/// 1つの変換で作ってよいものの上限。`None`なら上限なし
+///
+/// 共通部分や補集合のために途中で作るDFAも、すべて合わせて数える。
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// DFAの状態数の上限
+    pub max_states: Option<usize>,
+    /// 遷移を計算する回数((状態, 文字)の組の数)の上限。計算時間の目安になる。
+    /// Thompson構成で作るNFAの状態と遷移規則も、1つにつき1回と数える
+    pub max_steps: Option<usize>,
 }
 
-impl Default for DfaStateProvider {
-    fn default() -> Self {
+/// 1つの変換でここまでに作ったDFAの状態と、遷移を計算した回数
+///
+/// 部分集合構成法や直積の構成に同じものを渡していき、合わせて`Limits`を超えたら中断させる。
+#[derive(Debug, Default, Clone)]
+pub struct Budget {
+    limits: Limits,
+    states: usize,
+    steps: usize,
+}
+
+impl Budget {
+    pub fn new(limits: Limits) -> Self {
         Self {
-            states: HashMap::new(),
-            current: 0,
+            limits,
+            states: 0,
+            steps: 0,
         }
     }
+
+    /// ここまでに作ったDFAの状態の数
+    pub fn states(&self) -> usize {
+        self.states
+    }
+
+    /// ここまでに遷移を計算した回数
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+
+    /// DFAの状態を1つ作る。上限に達していれば`Error::StateExplosion`を返す。
+    /// `pending`は遷移を計算し終えていない状態の数で、中断したときに報告する
+    pub fn add_state(&mut self, pending: usize) -> Result<(), Error> {
+        if self.limits.max_states.is_some_and(|max| self.states >= max) {
+            return Err(self.explosion(pending));
+        }
+        self.states += 1;
+        Ok(())
+    }
+
+    /// 遷移を`count`回計算する。上限を超えるなら`Error::StateExplosion`を返す
+    pub fn add_steps(&mut self, count: usize, pending: usize) -> Result<(), Error> {
+        if self
+            .limits
+            .max_steps
+            .is_some_and(|max| self.steps + count > max)
+        {
+            return Err(self.explosion(pending));
+        }
+        self.steps += count;
+        Ok(())
+    }
+
+    fn explosion(&self, pending: usize) -> Error {
+        Error::StateExplosion {
+            limits: self.limits,
+            states: self.states,
+            steps: self.steps,
+            pending,
+        }
+    }
+}
+
+/// 部分集合構成法での、NFAの状態の集合とDFAの状態の対応
+#[derive(Default)]
+pub struct DfaStateProvider {
+    states: HashMap<BTreeSet<State>, dfa::State>,
+    current: dfa::State,
 }
 
 impl DfaStateProvider {
-    pub fn to_dfa_state(&mut self, nfa_state_set: BTreeSet<State>) -> dfa::State {
+    pub fn get_dfa_state(&mut self, nfa_state_set: BTreeSet<State>) -> dfa::State {
         if let Some(state) = self.states.get(&nfa_state_set) {
             *state
         } else {
@@ -165,6 +408,17 @@ impl DfaStateProvider {
         }
     }
 
+    /// (NFAの状態の集合, DFAの状態)の組をDFAの状態の順に並べたもの
+    pub fn to_vec(&self) -> Vec<(BTreeSet<State>, dfa::State)> {
+        let mut v: Vec<_> = self
+            .states
+            .iter()
+            .map(|(nfa_states, dfa_state)| (nfa_states.clone(), *dfa_state))
+            .collect();
+        v.sort_by_key(|(_, dfa_state)| *dfa_state);
+        v
+    }
+
     pub fn get_dfa_finishes(&self, nfa_finish: State) -> BTreeSet<dfa::State> {
         let mut v = BTreeSet::new();
         for (nfa_states, dfa_state) in &self.states {
@@ -179,7 +433,7 @@ impl DfaStateProvider {
 impl Display for DfaStateProvider {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.write_str("NFA states <=> DFA state\n")?;
-        for (nfa_states, dfa_state) in &self.states {
+        for (nfa_states, dfa_state) in self.to_vec() {
             f.write_fmt(format_args!("{:?}\t{}\n", nfa_states, dfa_state))?;
         }
         Ok(())
@@ -0,0 +1,150 @@
+//! NFAの遷移規則の索引
+
+use std::collections::{BTreeSet, HashSet};
+
+use super::{Label, Nfa, State};
+
+/// NFAの遷移規則を遷移元の状態ごとにまとめた索引(CSR形式)。`Nfa::adjacency`で一度だけ作る
+///
+/// 状態の番号は連続しているとは限らないので、状態を小さい順に0から数え直した添字で扱う。
+/// 添字の順と状態の順は同じなので、整列した添字の列は整列した状態の列に対応する。
+#[derive(Debug)]
+pub struct Adjacency {
+    /// 添字 => 状態
+    states: Vec<State>,
+    /// 添字`i`の状態から出る遷移は`edges[offsets[i]..offsets[i + 1]]`
+    offsets: Vec<usize>,
+    /// (ラベル, 遷移先の添字)。遷移元ごとにラベルの順に並べるので、ε遷移が先頭に来る
+    edges: Vec<(Label, usize)>,
+}
+
+impl Adjacency {
+    pub fn new(nfa: &Nfa) -> Self {
+        let states: Vec<State> = nfa.states().into_iter().collect();
+        let index = |s: State| states.binary_search(&s).unwrap();
+
+        let mut offsets = vec![0; states.len() + 1];
+        for rule in &nfa.rules {
+            offsets[index(rule.from) + 1] += 1;
+        }
+        for i in 0..states.len() {
+            offsets[i + 1] += offsets[i];
+        }
+        let mut edges = vec![(Label::Epsilon, 0); nfa.rules.len()];
+        let mut next = offsets.clone();
+        for rule in &nfa.rules {
+            let i = index(rule.from);
+            edges[next[i]] = (rule.label, index(rule.to));
+            next[i] += 1;
+        }
+        for i in 0..states.len() {
+            edges[offsets[i]..offsets[i + 1]].sort_unstable();
+        }
+
+        Self {
+            states,
+            offsets,
+            edges,
+        }
+    }
+
+    /// 状態の数
+    pub fn len(&self) -> usize {
+        self.states.len()
+    }
+
+    /// 状態がないかどうか。開始状態と受理状態があるので、NFAから作った索引では常に`false`
+    pub fn is_empty(&self) -> bool {
+        self.states.is_empty()
+    }
+
+    /// 状態`state`の添字。NFAにない状態なら`None`
+    pub fn position(&self, state: State) -> Option<usize> {
+        self.states.binary_search(&state).ok()
+    }
+
+    /// NFAにある状態`state`の添字
+    pub fn index(&self, state: State) -> usize {
+        self.position(state).unwrap()
+    }
+
+    /// 添字`i`の状態
+    pub fn state(&self, i: usize) -> State {
+        self.states[i]
+    }
+
+    /// 添字の列を状態の集合にする
+    pub fn to_states(&self, indices: &[usize]) -> BTreeSet<State> {
+        indices.iter().map(|i| self.states[*i]).collect()
+    }
+
+    /// 添字`i`の状態から`label`で遷移できる状態の添字
+    pub fn targets(&self, i: usize, label: Label) -> impl Iterator<Item = usize> + '_ {
+        let edges = &self.edges[self.offsets[i]..self.offsets[i + 1]];
+        let start = edges.partition_point(|(l, _)| *l < label);
+        edges[start..]
+            .iter()
+            .take_while(move |(l, _)| *l == label)
+            .map(|(_, t)| *t)
+    }
+}
+
+/// 状態ごとのε閉包を一度だけ計算して覚えておく。部分集合構成法やシミュレーションで使う
+pub struct Closures<'a> {
+    adjacency: &'a Adjacency,
+    /// 添字 => その状態のε閉包
+    closures: Vec<Option<Vec<usize>>>,
+}
+
+impl<'a> Closures<'a> {
+    pub fn new(adjacency: &'a Adjacency) -> Self {
+        Self {
+            adjacency,
+            closures: vec![None; adjacency.len()],
+        }
+    }
+
+    pub fn adjacency(&self) -> &'a Adjacency {
+        self.adjacency
+    }
+
+    /// 添字`i`の状態のε閉包。ε遷移をワークリストで辿って求め、結果を覚えておく
+    pub fn closure_of(&mut self, i: usize) -> &[usize] {
+        if self.closures[i].is_none() {
+            let mut visited = HashSet::from([i]);
+            let mut worklist = vec![i];
+            while let Some(s) = worklist.pop() {
+                for t in self.adjacency.targets(s, Label::Epsilon) {
+                    if visited.insert(t) {
+                        worklist.push(t);
+                    }
+                }
+            }
+            let mut closure: Vec<usize> = visited.into_iter().collect();
+            closure.sort_unstable();
+            self.closures[i] = Some(closure);
+        }
+        self.closures[i].as_deref().unwrap()
+    }
+
+    /// 添字の集合`set`のε閉包を、整列した添字の列で返す
+    pub fn closure(&mut self, set: &[usize]) -> Vec<usize> {
+        let mut closure = vec![];
+        for i in set {
+            closure.extend_from_slice(self.closure_of(*i));
+        }
+        closure.sort_unstable();
+        closure.dedup();
+        closure
+    }
+
+    /// 添字の集合`set`から文字`c`で遷移した先のε閉包。`Nfa::dfa_edge`と同じものを添字で求める
+    pub fn step(&mut self, set: &[usize], c: char) -> Vec<usize> {
+        let adjacency = self.adjacency;
+        let targets: Vec<usize> = set
+            .iter()
+            .flat_map(|i| adjacency.targets(*i, Label::Symbol(c)))
+            .collect();
+        self.closure(&targets)
+    }
+}
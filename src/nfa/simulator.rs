@@ -0,0 +1,58 @@
+//! DFAに変換せずにNFAへ文字列を読み込ませる
+
+use std::collections::BTreeSet;
+
+use super::{adjacency::Closures, Nfa, State};
+
+/// NFAの遷移規則の索引とε閉包を使い回し、多くの文字列をNFAのまま読み込ませる。`Nfa::simulator`で作る
+pub struct NfaSimulator<'a> {
+    closures: Closures<'a>,
+    /// 開始状態の添字
+    start: usize,
+    /// 受理状態の添字
+    finish: usize,
+}
+
+impl Nfa {
+    /// 多くの文字列を読み込ませるためのシミュレータを作る
+    pub fn simulator(&self) -> NfaSimulator<'_> {
+        let adjacency = self.adjacency();
+        NfaSimulator {
+            closures: Closures::new(adjacency),
+            start: adjacency.index(self.start),
+            finish: adjacency.index(self.finish),
+        }
+    }
+}
+
+impl NfaSimulator<'_> {
+    /// 取りうる状態の集合を持ち回って文字列`input`を読み込ませる
+    ///
+    /// 返すのは開始状態のε閉包と、1文字読むごとの状態の集合の列。
+    /// 集合が空になったらその先は読まずに打ち切るので、列は`input`の文字数+1より短くなることがある。
+    pub fn run_trace(&mut self, input: &str) -> Vec<BTreeSet<State>> {
+        let adjacency = self.closures.adjacency();
+        let mut current = self.closures.closure(&[self.start]);
+        let mut trace = vec![adjacency.to_states(&current)];
+        for c in input.chars() {
+            if current.is_empty() {
+                break;
+            }
+            current = self.closures.step(&current, c);
+            trace.push(adjacency.to_states(&current));
+        }
+        trace
+    }
+
+    /// 文字列`input`を受理するかどうか。状態の集合の列は記録しない
+    pub fn accepts(&mut self, input: &str) -> bool {
+        let mut current = self.closures.closure(&[self.start]);
+        for c in input.chars() {
+            if current.is_empty() {
+                return false;
+            }
+            current = self.closures.step(&current, c);
+        }
+        current.contains(&self.finish)
+    }
+}
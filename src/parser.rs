@@ -1,23 +1,343 @@
-use crate::regexpr::RegExpr;
+use crate::{diagnostic::Diagnostic, error::Error, regexpr::RegExpr};
 use nom::{
     branch::alt,
-    character::complete::{char, multispace0, one_of},
-    combinator::{eof, map},
-    error::ParseError,
+    bytes::complete::take_till,
+    character::complete::{char, digit1, multispace0, satisfy},
+    combinator::{all_consuming, consumed, eof, map, opt, peek, rest, value},
+    error::{ErrorKind, ParseError},
     multi::many0,
     sequence::{delimited, pair, preceded, terminated},
-    IResult, Parser,
+    Finish, IResult, Parser,
 };
 
-pub fn parse_expr_until_end(input: &str) -> IResult<&str, RegExpr> {
-    terminated(parse_expr, eof)(input)
+/// 演算子などに使う文字。文字として使うには`\`でエスケープするか`'`で囲む
+const META: &str = "()|&~*+?{}[].φε\\'";
+
+/// `{n,m}`に書ける繰り返しの回数の上限。展開すると部分式をこの回数だけ複製するので、大きすぎる回数は受け付けない
+pub const MAX_REPEAT: usize = 1000;
+
+/// 繰り返しを展開した後の正規表現の節の数の上限。
+/// `{n,m}`を入れ子にすると回数の積だけ複製されるので、`MAX_REPEAT`とは別に制限する
+pub const MAX_EXPANDED: usize = 100_000;
+
+/// エスケープせずに1文字として使える文字かどうか
+fn is_literal(c: char) -> bool {
+    !c.is_whitespace() && !META.contains(c)
+}
+
+/// 文字`c`を、パースし直すと同じ文字になるように正規表現の中に書く形にする
+pub(crate) fn escape(c: char) -> String {
+    match c {
+        '\n' => "\\n".to_string(),
+        '\t' => "\\t".to_string(),
+        c if c.is_control() || (c.is_whitespace() && c != ' ') => {
+            format!("\\u{{{:x}}}", c as u32)
+        }
+        c if is_literal(c) => c.to_string(),
+        c => format!("\\{}", c),
+    }
+}
+
+/// 文字`c`を、パースし直すと同じ文字になるように文字クラス`[...]`の中に書く形にする
+pub(crate) fn escape_in_class(c: char) -> String {
+    match c {
+        '-' | '^' => format!("\\{}", c),
+        c => escape(c),
+    }
+}
+
+/// 文字の集合の指定をパースする。`abc`のように文字を並べるか、`a-z`のように範囲で書く。
+/// 文字の書き方は文字クラス`[...]`の中と同じ
+pub fn parse_alphabet(input: &str) -> Result<Vec<char>, Error> {
+    let (_, ranges) = terminated(many0(ws(parse_class_item)), end_of_alphabet)(input)
+        .finish()
+        .map_err(|e| Error::Parse(e.into_diagnostic(input)))?;
+    let mut v: Vec<char> = ranges.into_iter().flat_map(|(a, b)| a..=b).collect();
+    v.sort();
+    v.dedup();
+    Ok(v)
+}
+
+/// 文字列全体を正規表現としてパースする
+pub fn parse(input: &str) -> Result<RegExpr, Error> {
+    terminated(|i| parse_expr(i, None), end_of_input)(input)
+        .finish()
+        .map(|(_, r)| r)
+        .map_err(|e| Error::Parse(e.into_diagnostic(input)))
 }
 
-pub fn parse_expr(input: &str) -> IResult<&str, RegExpr> {
+type PResult<'a, O> = IResult<&'a str, O, SyntaxError<'a>>;
+
+/// パースの誤り。入力全体と突き合わせて`Diagnostic`にする
+///
+/// nomの組み込みのパーサーが失敗したときは`Problem::Syntax`の`nom::Err::Error`になり、
+/// `alt`などがほかの書き方を試す。書き方が決まった後の誤りは`nom::Err::Failure`で返し、
+/// その場でパースを打ち切る。
+#[derive(Debug)]
+struct SyntaxError<'a> {
+    /// 誤りの箇所から始まる残りの入力
+    input: &'a str,
+    /// 誤りの箇所のバイト数
+    len: usize,
+    problem: Problem,
+}
+
+/// パースの誤りの内容
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Problem {
+    /// 具体的な診断のない構文エラー
+    Syntax,
+    /// 正規表現が空
+    EmptyExpr,
+    /// 演算子の後に正規表現がない
+    MissingAfter(char),
+    /// 演算子の前に正規表現がない
+    MissingBefore(char),
+    /// 後置演算子の前に繰り返す正規表現がない
+    MissingRepeated(char),
+    /// 中身のない`()`
+    EmptyParens,
+    /// 正規表現が来るべき位置で入力が終わった
+    UnexpectedEnd,
+    /// 閉じられていない`(`
+    UnclosedParen,
+    /// 対応する開き括弧がない閉じ括弧
+    Unmatched(char),
+    /// 正規表現の続きとして読めない文字
+    UnexpectedChar(char),
+    /// 入力の最後の`\`
+    TrailingBackslash,
+    /// 書き方が正しくないか、文字の番号が範囲外の`\u{...}`
+    InvalidUnicode,
+    /// `\`の後の、エスケープとして使えない英数字
+    UnknownEscape(char),
+    /// 閉じられていない`'`
+    UnclosedQuote,
+    /// 中身のない`''`
+    EmptyQuote,
+    /// 閉じられていない`[`
+    UnclosedClass,
+    /// 中身のない`[]`
+    EmptyClass,
+    /// 文字クラスの中の、範囲の始まりがない`-`
+    MissingRangeStart,
+    /// 文字クラスの中の、範囲の終わりがない`-`
+    MissingRangeEnd,
+    /// 始まりが終わりより後にある範囲
+    ReversedRange(char, char),
+    /// 文字クラスの中で、エスケープせずに書けない文字
+    UnexpectedInClass(char),
+    /// 閉じられていない`{`
+    UnclosedBounds,
+    /// 書き方が正しくない繰り返しの回数
+    InvalidBounds,
+    /// 下限が上限より大きい繰り返しの回数
+    ReversedBounds(usize, usize),
+    /// `MAX_REPEAT`を超える繰り返しの回数
+    TooManyRepetitions,
+    /// 展開した後の節の数が`MAX_EXPANDED`を超える繰り返し
+    TooLargeExpansion,
+}
+
+impl Problem {
+    /// 誤りの箇所の列番号が`column`のときのメッセージ
+    fn message(self, column: usize) -> String {
+        match self {
+            Problem::Syntax => "構文エラー".to_string(),
+            Problem::EmptyExpr => "正規表現が空".to_string(),
+            Problem::MissingAfter(c) => format!("'{}'の後に正規表現がない", c),
+            Problem::MissingBefore(c) => format!("'{}'の前に正規表現がない", c),
+            Problem::MissingRepeated(c) => format!("'{}'の前に繰り返す正規表現がない", c),
+            Problem::EmptyParens => "括弧の中が空".to_string(),
+            Problem::UnexpectedEnd => "正規表現が途中で終わっている".to_string(),
+            Problem::UnclosedParen => format!("列{}で開いた'('が閉じられていない", column),
+            Problem::Unmatched(c) => {
+                let open = match c {
+                    ')' => '(',
+                    ']' => '[',
+                    _ => '{',
+                };
+                format!("対応する'{}'がない'{}'", open, c)
+            }
+            Problem::UnexpectedChar(c) | Problem::UnexpectedInClass(c) => {
+                format!("予期しない文字'{}'", c)
+            }
+            Problem::TrailingBackslash => "'\\'の後に文字がない".to_string(),
+            Problem::InvalidUnicode => "不正なUnicodeエスケープ".to_string(),
+            Problem::UnknownEscape(c) => format!("不明なエスケープ'\\{}'", c),
+            Problem::UnclosedQuote => "'で囲んだ文字列が閉じられていない".to_string(),
+            Problem::EmptyQuote => "''の中が空".to_string(),
+            Problem::UnclosedClass => "'['が閉じられていない".to_string(),
+            Problem::EmptyClass => "[]の中が空".to_string(),
+            Problem::MissingRangeStart => "範囲の始まりがない'-'".to_string(),
+            Problem::MissingRangeEnd => "'-'の後に範囲の終わりがない".to_string(),
+            Problem::ReversedRange(first, last) => format!(
+                "範囲{}-{}の始まりが終わりより後にある",
+                escape_in_class(first),
+                escape_in_class(last)
+            ),
+            Problem::UnclosedBounds => "'{'が閉じられていない".to_string(),
+            Problem::InvalidBounds => "繰り返しの回数の書き方が正しくない".to_string(),
+            Problem::ReversedBounds(min, max) => {
+                format!("繰り返しの回数の下限{}が上限{}より大きい", min, max)
+            }
+            Problem::TooManyRepetitions => {
+                format!("繰り返しの回数が上限の{}を超えている", MAX_REPEAT)
+            }
+            Problem::TooLargeExpansion => format!(
+                "繰り返しを展開すると正規表現の大きさが上限の{}を超える",
+                MAX_EXPANDED
+            ),
+        }
+    }
+
+    /// 修正方法の提案
+    fn help(self) -> Option<String> {
+        const BOUNDS: &str = "繰り返しの回数は{3}、{3,}、{3,5}のように書いてください";
+        let help = match self {
+            Problem::EmptyExpr => "空文字列はε、何も受理しない正規表現はφと書いてください",
+            Problem::EmptyParens => "空文字列はεと書いてください",
+            Problem::UnclosedParen => "対応する')'を追加してください",
+            Problem::UnexpectedChar(c) => {
+                return Some(format!(
+                    "文字として使うには\\{}か'{}'と書いてください",
+                    c, c
+                ))
+            }
+            Problem::UnexpectedInClass(c) => {
+                return Some(format!("文字の{}は\\{}と書いてください", c, c))
+            }
+            Problem::TrailingBackslash => "文字の\\は\\\\と書いてください",
+            Problem::InvalidUnicode => "\\u{3042}のように16進数で文字の番号を書いてください",
+            Problem::UnknownEscape(_) => "\\の後に書けるのは記号、空白、n、t、u{...}です",
+            Problem::UnclosedQuote => "文字列の終わりに'を追加してください",
+            Problem::EmptyQuote => "'の中には1文字以上書いてください",
+            Problem::UnclosedClass => "対応する']'を追加してください",
+            Problem::EmptyClass => "[]の中には1文字以上書いてください",
+            Problem::MissingRangeStart => "文字の-は\\-と書いてください",
+            Problem::MissingRangeEnd => "範囲はa-zのように書いてください",
+            Problem::UnclosedBounds | Problem::InvalidBounds => BOUNDS,
+            Problem::TooManyRepetitions | Problem::TooLargeExpansion => {
+                "回数を減らすか、*や+を使ってください"
+            }
+            _ => return None,
+        };
+        Some(help.to_string())
+    }
+}
+
+impl<'a> SyntaxError<'a> {
+    fn new(input: &'a str, len: usize, problem: Problem) -> Self {
+        Self {
+            input,
+            len,
+            problem,
+        }
+    }
+
+    /// パースした入力全体`source`での位置を求めて診断情報にする
+    fn into_diagnostic(self, source: &str) -> Diagnostic {
+        let start = source.len() - self.input.len();
+        let mut d = Diagnostic::new(source, start, start + self.len, "");
+        d.message = self.problem.message(d.column);
+        match self.problem.help() {
+            Some(help) => d.with_help(help),
+            None => d,
+        }
+    }
+}
+
+impl<'a> ParseError<&'a str> for SyntaxError<'a> {
+    fn from_error_kind(input: &'a str, _: ErrorKind) -> Self {
+        Self::new(input, 0, Problem::Syntax)
+    }
+
+    fn append(_: &'a str, _: ErrorKind, other: Self) -> Self {
+        other
+    }
+
+    /// どの書き方でも読めなかったときは、最も先まで読めた位置を残す
+    fn or(self, other: Self) -> Self {
+        if other.input.len() < self.input.len() {
+            other
+        } else {
+            self
+        }
+    }
+}
+
+/// 入力`input`の先頭から`len`バイトに`problem`があるとして、パースを打ち切る
+fn fail<'a, O>(input: &'a str, len: usize, problem: Problem) -> PResult<'a, O> {
+    Err(nom::Err::Failure(SyntaxError::new(input, len, problem)))
+}
+
+/// `nom::combinator::cut`と同じく`parser`が読めなければパースを打ち切るが、
+/// 誤りは`parser`に渡した入力から`diagnose`で作り直す
+fn cut_with<'a, O>(
+    mut parser: impl Parser<&'a str, O, SyntaxError<'a>>,
+    diagnose: impl Fn(&'a str) -> SyntaxError<'a>,
+) -> impl FnMut(&'a str) -> PResult<'a, O> {
+    move |input| match parser.parse(input) {
+        Err(nom::Err::Error(_)) => Err(nom::Err::Failure(diagnose(input))),
+        r => r,
+    }
+}
+
+/// `multispace0`と同じく空白を読み飛ばす
+fn skip_ws(input: &str) -> &str {
+    input.trim_start_matches([' ', '\t', '\r', '\n'])
+}
+
+/// 空白に続く記号`c`。記号から始まる残りの入力を返し、診断で記号の位置を示すのに使う
+fn symbol<'a>(c: char) -> impl FnMut(&'a str) -> PResult<'a, &'a str> {
+    preceded(multispace0, terminated(peek(rest), char(c)))
+}
+
+/// 正規表現の終わり。続く文字があれば、それが読めない理由を診断する
+fn end_of_input(input: &str) -> PResult<'_, ()> {
+    cut_with(value((), preceded(multispace0, eof)), unexpected)(input)
+}
+
+/// 正規表現の続きとして読めなかった、空白に続く文字の診断
+fn unexpected(input: &str) -> SyntaxError<'_> {
+    let input = skip_ws(input);
+    match input.chars().next() {
+        None => SyntaxError::new(input, 0, Problem::UnexpectedEnd),
+        Some(c @ (')' | ']' | '}')) => SyntaxError::new(input, 1, Problem::Unmatched(c)),
+        Some(c) => SyntaxError::new(input, c.len_utf8(), Problem::UnexpectedChar(c)),
+    }
+}
+
+/// 正規表現が来るべき位置`input`に正規表現がないときの診断。`prev`は直前の演算子から始まる入力
+fn missing_operand<'a>(input: &'a str, prev: Option<&'a str>) -> SyntaxError<'a> {
+    let next = skip_ws(input);
+    let prev = prev.and_then(|p| Some((p, p.chars().next()?)));
+    match (prev, next.chars().next()) {
+        (_, Some(c @ (']' | '}'))) => SyntaxError::new(next, 1, Problem::Unmatched(c)),
+        (Some((p, c @ ('|' | '&' | '~'))), _) => SyntaxError::new(p, 1, Problem::MissingAfter(c)),
+        (Some((p, '(')), Some(')')) => {
+            SyntaxError::new(p, p.len() - next.len() + 1, Problem::EmptyParens)
+        }
+        (_, Some(c @ ('*' | '+' | '?' | '{'))) => {
+            SyntaxError::new(next, 1, Problem::MissingRepeated(c))
+        }
+        (_, Some(c)) => SyntaxError::new(next, c.len_utf8(), Problem::MissingBefore(c)),
+        (None, None) => SyntaxError::new(input, input.len(), Problem::EmptyExpr),
+        // `(`の直後で入力が終わった
+        (Some((p, '(')), None) => SyntaxError::new(p, 1, Problem::UnclosedParen),
+        (Some(_), None) => SyntaxError::new(next, 0, Problem::UnexpectedEnd),
+    }
+}
+
+/// `|`で区切った正規表現。`prev`は直前の演算子や`(`から始まる入力で、正規表現がないときの診断に使う
+fn parse_expr<'a>(input: &'a str, prev: Option<&'a str>) -> PResult<'a, RegExpr> {
     map(
         pair(
-            ws(parse_orterm),
-            many0(preceded(ws(char('|')), parse_orterm)),
+            |i| parse_orterm(i, prev),
+            many0(|i| {
+                let (i, op) = symbol('|')(i)?;
+                parse_orterm(i, Some(op))
+            }),
         ),
         |(r, mut rv)| {
             let mut v = vec![r];
@@ -27,47 +347,274 @@ pub fn parse_expr(input: &str) -> IResult<&str, RegExpr> {
     )(input)
 }
 
-fn parse_orterm(input: &str) -> IResult<&str, RegExpr> {
+fn parse_orterm<'a>(input: &'a str, prev: Option<&'a str>) -> PResult<'a, RegExpr> {
     map(
-        pair(ws(parse_catterm), many0(parse_catterm)),
+        pair(
+            |i| parse_andterm(i, prev),
+            many0(|i| {
+                let (i, op) = symbol('&')(i)?;
+                parse_andterm(i, Some(op))
+            }),
+        ),
         |(r, mut rv)| {
-            let mut v = vec![r];
-            v.append(&mut rv);
-            RegExpr::Cat(v)
+            if rv.is_empty() {
+                // 共通部分はDFAを経由して構成するので、演算子がなければそのままにしておく
+                r
+            } else {
+                let mut v = vec![r];
+                v.append(&mut rv);
+                RegExpr::And(v)
+            }
         },
     )(input)
 }
 
-fn parse_catterm(input: &str) -> IResult<&str, RegExpr> {
+fn parse_andterm<'a>(input: &'a str, prev: Option<&'a str>) -> PResult<'a, RegExpr> {
+    map(pair(operand(prev), many0(parse_catterm)), |(r, mut rv)| {
+        let mut v = vec![r];
+        v.append(&mut rv);
+        RegExpr::Cat(v)
+    })(input)
+}
+
+/// 正規表現が来なければならない位置の`parse_catterm`。読めなければ`missing_operand`で診断する
+fn operand<'a>(prev: Option<&'a str>) -> impl FnMut(&'a str) -> PResult<'a, RegExpr> {
+    cut_with(ws(parse_catterm), move |input| missing_operand(input, prev))
+}
+
+fn parse_catterm(input: &str) -> PResult<'_, RegExpr> {
+    alt((
+        |i| {
+            let (i, op) = symbol('~')(i)?;
+            map(operand(Some(op)), |r| RegExpr::Not(Box::new(r)))(i)
+        },
+        |i| {
+            let (rest, (r, postfixes)) = pair(
+                parse_repterm,
+                many0(ws(pair(peek(rest), consumed(parse_postfix)))),
+            )(i)?;
+            let r = postfixes
+                .into_iter()
+                .try_fold(r, |r, (at, (span, postfix))| {
+                    let r = Box::new(r);
+                    let r = match postfix {
+                        Postfix::Repeat => RegExpr::Repeat(r),
+                        Postfix::Plus => RegExpr::Plus(r),
+                        Postfix::Optional => RegExpr::Optional(r),
+                        Postfix::Bounded(min, max) => RegExpr::Bounded { expr: r, min, max },
+                    };
+                    // 入れ子の繰り返しは展開すると回数の積だけ大きくなるので、展開する前に大きさを確かめる
+                    if r.desugared_size() > MAX_EXPANDED {
+                        return Err(nom::Err::Failure(SyntaxError::new(
+                            at,
+                            span.len(),
+                            Problem::TooLargeExpansion,
+                        )));
+                    }
+                    Ok(r)
+                })?;
+            Ok((rest, r))
+        },
+    ))(input)
+}
+
+/// 後置演算子
+#[derive(Debug, Clone, Copy)]
+enum Postfix {
+    Repeat,
+    Plus,
+    Optional,
+    Bounded(usize, Option<usize>),
+}
+
+fn parse_postfix(input: &str) -> PResult<'_, Postfix> {
     alt((
-        map(terminated(parse_repterm, ws(char('*'))), |r| {
-            RegExpr::Repeat(Box::new(r))
+        value(Postfix::Repeat, char('*')),
+        value(Postfix::Plus, char('+')),
+        value(Postfix::Optional, char('?')),
+        parse_bounds,
+    ))(input)
+}
+
+/// `{n}`、`{n,}`、`{n,m}`。`{`の後に誤りがあればその場で診断する。回数は`MAX_REPEAT`まで
+fn parse_bounds(input: &str) -> PResult<'_, Postfix> {
+    let (rest, bounds) = preceded(
+        char('{'),
+        cut_with(terminated(take_till(|c| c == '}'), char('}')), |_| {
+            SyntaxError::new(input, 1, Problem::UnclosedBounds)
         }),
-        parse_repterm,
+    )(input)?;
+    let len = input.len() - rest.len();
+    // 桁が多すぎて数にできない回数は、上限を超えたものとして扱う
+    let number = || map(digit1, |s: &str| s.parse::<usize>().unwrap_or(usize::MAX));
+    let (_, (min, max)) = cut_with(
+        all_consuming(pair(number(), opt(preceded(char(','), opt(number()))))),
+        |_| SyntaxError::new(input, len, Problem::InvalidBounds),
+    )(bounds)?;
+    let max = max.unwrap_or(Some(min));
+    if min.max(max.unwrap_or(0)) > MAX_REPEAT {
+        return fail(input, len, Problem::TooManyRepetitions);
+    }
+    match max {
+        Some(max) if min > max => fail(input, len, Problem::ReversedBounds(min, max)),
+        max => Ok((rest, Postfix::Bounded(min, max))),
+    }
+}
+
+fn parse_repterm(input: &str) -> PResult<'_, RegExpr> {
+    alt((
+        parse_repterm_par,
+        parse_repterm_empty,
+        parse_repterm_epsilon,
+        parse_repterm_class,
+        parse_repterm_char,
     ))(input)
 }
 
-fn parse_repterm(input: &str) -> IResult<&str, RegExpr> {
-    alt((parse_repterm_par, parse_repterm_empty, parse_repterm_char))(input)
+/// `[abc]`、`[a-z]`、`[^0-9]`のような文字クラスと、任意の1文字`.`
+fn parse_repterm_class(input: &str) -> PResult<'_, RegExpr> {
+    ws(alt((parse_class, value(RegExpr::Any, char('.'))))).parse(input)
 }
 
-fn parse_repterm_par(input: &str) -> IResult<&str, RegExpr> {
-    delimited(ws(char('(')), parse_expr, ws(char(')')))(input)
+/// `[`から始まる文字クラス。`[`の後に誤りがあればその場で診断する
+fn parse_class(input: &str) -> PResult<'_, RegExpr> {
+    let (rest, (negated, ranges)) =
+        preceded(char('['), pair(opt(char('^')), many0(ws(parse_class_item))))(input)?;
+    let (rest, _) = cut_with(preceded(multispace0, char(']')), |_| {
+        SyntaxError::new(input, 1, Problem::UnclosedClass)
+    })(rest)?;
+    if ranges.is_empty() {
+        return fail(input, input.len() - rest.len(), Problem::EmptyClass);
+    }
+    Ok((
+        rest,
+        RegExpr::Class {
+            negated: negated.is_some(),
+            ranges,
+        },
+    ))
 }
 
-fn parse_repterm_char(input: &str) -> IResult<&str, RegExpr> {
-    map(
-        ws(one_of(
-            "0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ",
-        )),
-        |c| RegExpr::Char(c),
-    )(input)
+/// 文字クラスの中の`a`や`a-z`。範囲の始まりと終わりの組を返す
+fn parse_class_item(input: &str) -> PResult<'_, (char, char)> {
+    let (dash, first) = parse_class_char(input)?;
+    let (rest, last) = opt(preceded(
+        char('-'),
+        cut_with(
+            alt((
+                parse_escape,
+                satisfy(|c| !c.is_whitespace() && !"[]^-\\".contains(c)),
+            )),
+            |_| SyntaxError::new(dash, 1, Problem::MissingRangeEnd),
+        ),
+    ))(dash)?;
+    let last = last.unwrap_or(first);
+    if first > last {
+        return fail(
+            input,
+            input.len() - rest.len(),
+            Problem::ReversedRange(first, last),
+        );
+    }
+    Ok((rest, (first, last)))
+}
+
+/// 文字クラスの中の1文字。エスケープせずに書けない文字があればその場で診断する
+fn parse_class_char(input: &str) -> PResult<'_, char> {
+    match input.chars().next() {
+        Some('-') => fail(input, 1, Problem::MissingRangeStart),
+        Some(c @ ('[' | '^')) => fail(input, 1, Problem::UnexpectedInClass(c)),
+        _ => alt((
+            parse_escape,
+            satisfy(|c| !c.is_whitespace() && !"[]^-\\".contains(c)),
+        ))(input),
+    }
+}
+
+/// 文字の集合の指定の終わり。続く文字があれば診断する
+fn end_of_alphabet(input: &str) -> PResult<'_, ()> {
+    cut_with(value((), preceded(multispace0, eof)), |input| {
+        let input = skip_ws(input);
+        match input.chars().next() {
+            Some(c) => SyntaxError::new(input, c.len_utf8(), Problem::UnexpectedInClass(c)),
+            None => SyntaxError::new(input, 0, Problem::Syntax),
+        }
+    })(input)
+}
+
+fn parse_repterm_par(input: &str) -> PResult<'_, RegExpr> {
+    let (rest, open) = symbol('(')(input)?;
+    terminated(
+        |i| parse_expr(i, Some(open)),
+        cut_with(ws(char(')')), |i| match skip_ws(i) {
+            "" => SyntaxError::new(open, 1, Problem::UnclosedParen),
+            _ => unexpected(i),
+        }),
+    )(rest)
+}
+
+fn parse_repterm_char(input: &str) -> PResult<'_, RegExpr> {
+    ws(alt((
+        map(satisfy(is_literal), RegExpr::Char),
+        map(parse_escape, RegExpr::Char),
+        parse_quoted,
+    )))
+    .parse(input)
+}
+
+/// `\`に続く1文字、`\n`、`\t`、`\u{3042}`のようなエスケープ。`\`の後に誤りがあればその場で診断する
+fn parse_escape(input: &str) -> PResult<'_, char> {
+    let (rest, _) = char('\\')(input)?;
+    let Some(c) = rest.chars().next() else {
+        return fail(input, 1, Problem::TrailingBackslash);
+    };
+    match c {
+        'u' => {
+            let (rest, hex) =
+                opt(delimited(char('{'), take_till(|c| c == '}'), char('}')))(&rest[1..])?;
+            let len = input.len() - rest.len();
+            hex.filter(|hex| {
+                (1..=6).contains(&hex.len()) && hex.chars().all(|c| c.is_ascii_hexdigit())
+            })
+            .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+            .and_then(char::from_u32)
+            .map_or_else(
+                || fail(input, len, Problem::InvalidUnicode),
+                |c| Ok((rest, c)),
+            )
+        }
+        'n' => Ok((&rest[1..], '\n')),
+        't' => Ok((&rest[1..], '\t')),
+        c if c.is_ascii_alphanumeric() => fail(input, 1 + c.len_utf8(), Problem::UnknownEscape(c)),
+        c => Ok((&rest[c.len_utf8()..], c)),
+    }
+}
+
+/// `'+'`や`'if'`のように`'`で囲んだ文字列。中では`\`によるエスケープだけが使える
+fn parse_quoted(input: &str) -> PResult<'_, RegExpr> {
+    let (rest, chars) = preceded(
+        char('\''),
+        many0(alt((parse_escape, satisfy(|c| c != '\'' && c != '\\')))),
+    )(input)?;
+    let (rest, _) = cut_with(char('\''), |_| {
+        SyntaxError::new(input, 1, Problem::UnclosedQuote)
+    })(rest)?;
+    let r = match chars.len() {
+        0 => return fail(input, input.len() - rest.len(), Problem::EmptyQuote),
+        1 => RegExpr::Char(chars[0]),
+        _ => RegExpr::Cat(chars.into_iter().map(RegExpr::Char).collect()),
+    };
+    Ok((rest, r))
 }
 
-fn parse_repterm_empty(input: &str) -> IResult<&str, RegExpr> {
+fn parse_repterm_empty(input: &str) -> PResult<'_, RegExpr> {
     map(ws(char('φ')), |_| RegExpr::Empty)(input)
 }
 
+fn parse_repterm_epsilon(input: &str) -> PResult<'_, RegExpr> {
+    map(ws(char('ε')), |_| RegExpr::Epsilon)(input)
+}
+
 /// A combinator that takes a parser `inner` and produces a parser that also consumes both leading and
 /// trailing whitespace, returning the output of `inner`.
 fn ws<'a, F, O, E: ParseError<&'a str>>(inner: F) -> impl Parser<&'a str, O, E>
@@ -0,0 +1,159 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, VecDeque},
+    fmt::Display,
+};
+
+use crate::{
+    dfa::{Dfa, Rule, State},
+    error::Error,
+    nfa::Budget,
+};
+
+/// 直積オートマトンの受理状態を決める集合演算
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BoolOp {
+    /// 両方が受理する
+    Intersection,
+    /// 少なくとも片方が受理する
+    Union,
+    /// 左だけが受理する
+    Difference,
+    /// 片方だけが受理する
+    SymmetricDifference,
+}
+
+impl BoolOp {
+    pub fn apply(self, left: bool, right: bool) -> bool {
+        match self {
+            BoolOp::Intersection => left && right,
+            BoolOp::Union => left || right,
+            BoolOp::Difference => left && !right,
+            BoolOp::SymmetricDifference => left != right,
+        }
+    }
+}
+
+/// 直積オートマトン
+#[derive(Debug, PartialEq, Eq)]
+pub struct Product {
+    pub dfa: Dfa,
+    /// 直積DFAの状態 => (左のDFAの状態, 右のDFAの状態)。
+    /// `None`は遷移規則がなく行き詰まったことを表す
+    pub pairs: BTreeMap<State, (Option<State>, Option<State>)>,
+}
+
+impl Dfa {
+    /// 2つのDFAの直積オートマトンを作る
+    ///
+    /// 文字の集合は両者の和集合で揃える。遷移規則がない場合は行き詰まった(受理しない)ものとして扱うので、
+    /// 結果のDFAは揃えた文字の集合の上で完全になる。
+    pub fn product(&self, other: &Dfa, op: BoolOp) -> Product {
+        match self.product_with_budget(other, op, &mut Budget::default()) {
+            Ok(product) => product,
+            Err(_) => unreachable!("上限がなければ直積の構成は中断しない"),
+        }
+    }
+
+    /// `product`と同じだが、作った状態と遷移の計算を`budget`で数え、上限を超えたら中断する
+    pub fn product_with_budget(
+        &self,
+        other: &Dfa,
+        op: BoolOp,
+        budget: &mut Budget,
+    ) -> Result<Product, Error> {
+        let alphabets: BTreeSet<char> = self
+            .alphabets()
+            .union(&other.alphabets())
+            .copied()
+            .collect();
+        let accepts =
+            |dfa: &Dfa, state: Option<State>| state.is_some_and(|s| dfa.finish_states.contains(&s));
+
+        let mut states = HashMap::new();
+        let mut pairs = BTreeMap::new();
+        let mut queue = VecDeque::new();
+        let mut rules = BTreeSet::new();
+        let mut finish_states = BTreeSet::new();
+
+        budget.add_state(0)?;
+        let start = (Some(self.start), Some(other.start));
+        states.insert(start, 1);
+        pairs.insert(1, start);
+        queue.push_back(start);
+
+        while let Some(pair @ (left, right)) = queue.pop_front() {
+            let from = states[&pair];
+            if op.apply(accepts(self, left), accepts(other, right)) {
+                finish_states.insert(from);
+            }
+            for c in &alphabets {
+                // 取り出した組はまだ処理し終えていないので、未処理の数に含める
+                budget.add_steps(1, queue.len() + 1)?;
+                let next = (
+                    left.and_then(|s| self.next(s, *c)),
+                    right.and_then(|s| other.next(s, *c)),
+                );
+                let to = match states.get(&next) {
+                    Some(to) => *to,
+                    None => {
+                        budget.add_state(queue.len() + 1)?;
+                        let to = pairs.len() as State + 1;
+                        states.insert(next, to);
+                        pairs.insert(to, next);
+                        queue.push_back(next);
+                        to
+                    }
+                };
+                rules.insert(Rule {
+                    from,
+                    alphabet: *c,
+                    to,
+                });
+            }
+        }
+
+        Ok(Product {
+            dfa: Dfa {
+                start: 1,
+                finish_states,
+                rules,
+            },
+            pairs,
+        })
+    }
+
+    pub fn intersection(&self, other: &Dfa) -> Product {
+        self.product(other, BoolOp::Intersection)
+    }
+
+    pub fn union(&self, other: &Dfa) -> Product {
+        self.product(other, BoolOp::Union)
+    }
+
+    pub fn difference(&self, other: &Dfa) -> Product {
+        self.product(other, BoolOp::Difference)
+    }
+
+    pub fn symmetric_difference(&self, other: &Dfa) -> Product {
+        self.product(other, BoolOp::SymmetricDifference)
+    }
+}
+
+impl Display for Product {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let fmt_state = |state: &Option<State>| match state {
+            Some(state) => format!("{}", state),
+            None => "-".to_string(),
+        };
+        f.write_str("(left, right) <=> product DFA state\n")?;
+        for (state, (left, right)) in &self.pairs {
+            f.write_fmt(format_args!(
+                "({}, {})\t{}\n",
+                fmt_state(left),
+                fmt_state(right),
+                state
+            ))?;
+        }
+        f.write_fmt(format_args!("\n{}", self.dfa))
+    }
+}
@@ -1,25 +1,86 @@
 use std::fmt::Display;
 
-use crate::nfa::{GlobalEnv, Nfa, Rule};
+use crate::{
+    dfa::Dfa,
+    error::Error,
+    nfa::{Budget, GlobalEnv, Label, Limits, Nfa, Rule},
+    parser,
+    product::BoolOp,
+};
 
 ///
 /// # 正規表現のEBNF
 ///
 /// ```txt
 /// <expr> := <orterm> [ '|' <orterm> ]*
-/// <orterm> := <catterm> [ <catterm> ]*
-/// <catterm> := <repterm> [ '*' ]?
+/// <orterm> := <andterm> [ '&' <andterm> ]*
+/// <andterm> := <catterm> [ <catterm> ]*
+/// <catterm> := '~' <catterm>
+///            | <repterm> <postfix>*
+/// <postfix> := '*' | '+' | '?' | '{' 数 '}' | '{' 数 ',' '}' | '{' 数 ',' 数 '}'
 /// <repterm> := '(' <expr> ')'
-///            | 任意の1文字
+///            | <char>
+///            | '\'' ( '\''と'\'以外の任意の1文字 | <escape> )+ '\''
+///            | '[' [ '^' ]? <class-item>+ ']'
+///            | '.'
 ///            | φ
+///            | ε
+/// <class-item> := <class-char> [ '-' <class-char> ]?
+/// <class-char> := '[', ']', '^', '-', '\'と空白以外の任意の1文字
+///               | <escape>
+/// <char> := 演算子、予約文字、空白以外の任意の1文字
+///         | <escape>
+/// <escape> := '\' 半角英数字以外の任意の1文字
+///           | '\n' | '\t' | '\u{' 16進数 '}'
 /// ```
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum RegExpr {
+    /// 空集合φ。何も受理しない
     Empty,
+    /// 空文字列ε
+    Epsilon,
     Char(char),
     Cat(Vec<RegExpr>),
     Or(Vec<RegExpr>),
     Repeat(Box<RegExpr>),
+    /// 共通部分。DFAの直積で構成する
+    And(Vec<RegExpr>),
+    /// 考えている文字の集合全体(`GlobalEnv::alphabets`)の上での補集合。DFAの補集合で構成する
+    Not(Box<RegExpr>),
+    /// 文字クラス`[abc]`、`[a-z]`。範囲の始まりと終わりの組の一覧で表す。
+    /// `negated`なら考えている文字の集合全体(`GlobalEnv::alphabets`)の上での補集合`[^0-9]`
+    Class {
+        negated: bool,
+        ranges: Vec<(char, char)>,
+    },
+    /// 考えている文字の集合全体(`GlobalEnv::alphabets`)のどれか1文字`.`
+    Any,
+    /// 1回以上の繰り返し`A+`。`AA*`の糖衣構文
+    Plus(Box<RegExpr>),
+    /// 0回または1回の出現`A?`。`(ε|A)`の糖衣構文
+    Optional(Box<RegExpr>),
+    /// `min`回以上`max`回以下の繰り返し`A{n,m}`。`max`が`None`なら上限がない。
+    /// `A{2,3}`は`AA(ε|A)`の、`A{2,}`は`AAA*`の糖衣構文
+    Bounded {
+        expr: Box<RegExpr>,
+        min: usize,
+        max: Option<usize>,
+    },
+}
+
+/// 複数の正規表現に現れる文字を、重複なく整列して並べたもの
+pub fn get_alphabets_of(regexprs: &[&RegExpr]) -> Vec<char> {
+    get_alphabets_with(regexprs, &[])
+}
+
+/// 複数の正規表現に現れる文字と`extra`を合わせて、重複なく整列して並べたもの。
+/// `extra`は否定の文字クラスや`.`が表す文字の範囲を広げるのに使う
+pub fn get_alphabets_with(regexprs: &[&RegExpr], extra: &[char]) -> Vec<char> {
+    let mut v: Vec<char> = regexprs.iter().flat_map(|r| r.get_alphabets()).collect();
+    v.extend_from_slice(extra);
+    v.sort();
+    v.dedup();
+    v
 }
 
 pub fn cat_char(s: &str) -> RegExpr {
@@ -31,16 +92,125 @@ pub fn cat_char(s: &str) -> RegExpr {
 }
 
 impl RegExpr {
-    pub fn to_nfa(&self, env: &mut GlobalEnv) -> Nfa {
+    /// 糖衣構文を基本の演算だけを使った形に展開する
+    pub fn desugar(&self) -> RegExpr {
         match self {
+            RegExpr::Empty
+            | RegExpr::Epsilon
+            | RegExpr::Char(_)
+            | RegExpr::Class { .. }
+            | RegExpr::Any => self.clone(),
+            RegExpr::Cat(v) => RegExpr::Cat(v.iter().map(|r| r.desugar()).collect()),
+            RegExpr::Or(v) => RegExpr::Or(v.iter().map(|r| r.desugar()).collect()),
+            RegExpr::Repeat(r) => RegExpr::Repeat(Box::new(r.desugar())),
+            RegExpr::And(v) => RegExpr::And(v.iter().map(|r| r.desugar()).collect()),
+            RegExpr::Not(r) => RegExpr::Not(Box::new(r.desugar())),
+            RegExpr::Plus(_) | RegExpr::Optional(_) | RegExpr::Bounded { .. } => {
+                self.expand().desugar()
+            }
+        }
+    }
+
+    /// 糖衣構文を1段だけ展開する。部分式は展開せずに複製し、糖衣構文でなければそのまま複製する
+    fn expand(&self) -> RegExpr {
+        match self {
+            RegExpr::Plus(r) => RegExpr::Cat(vec![(**r).clone(), RegExpr::Repeat(r.clone())]),
+            RegExpr::Optional(r) => RegExpr::Or(vec![RegExpr::Epsilon, (**r).clone()]),
+            RegExpr::Bounded { expr, min, max } => {
+                let r = (**expr).clone();
+                let mut v = vec![r.clone(); *min];
+                match max {
+                    Some(max) => {
+                        for _ in *min..*max {
+                            v.push(RegExpr::Or(vec![RegExpr::Epsilon, r.clone()]));
+                        }
+                    }
+                    None => v.push(RegExpr::Repeat(Box::new(r))),
+                }
+                if v.is_empty() {
+                    RegExpr::Epsilon
+                } else {
+                    RegExpr::Cat(v)
+                }
+            }
+            _ => self.clone(),
+        }
+    }
+
+    /// `desugar`で展開した後の正規表現の節の数。展開せずに求め、`usize`に収まらなければ`usize::MAX`にする
+    pub fn desugared_size(&self) -> usize {
+        let sum = |v: &[RegExpr]| {
+            v.iter()
+                .fold(1, |n: usize, r| n.saturating_add(r.desugared_size()))
+        };
+        match self {
+            RegExpr::Empty
+            | RegExpr::Epsilon
+            | RegExpr::Char(_)
+            | RegExpr::Class { .. }
+            | RegExpr::Any => 1,
+            RegExpr::Cat(v) | RegExpr::Or(v) | RegExpr::And(v) => sum(v),
+            RegExpr::Repeat(r) | RegExpr::Not(r) => r.desugared_size().saturating_add(1),
+            // AA*
+            RegExpr::Plus(r) => r.desugared_size().saturating_add(1).saturating_mul(2),
+            // (ε|A)
+            RegExpr::Optional(r) => r.desugared_size().saturating_add(2),
+            RegExpr::Bounded { expr, min, max } => {
+                let n = expr.desugared_size();
+                let rest = match max {
+                    Some(0) => return 1,
+                    // (ε|A)を上限との差の数だけ並べる
+                    Some(max) => max.saturating_sub(*min).saturating_mul(n.saturating_add(2)),
+                    None => n.saturating_add(1),
+                };
+                n.saturating_mul(*min)
+                    .saturating_add(rest)
+                    .saturating_add(1)
+            }
+        }
+    }
+
+    pub fn to_nfa(&self, env: &mut GlobalEnv) -> Nfa {
+        match self.to_nfa_with_budget(env, &mut Budget::default()) {
+            Ok(nfa) => nfa,
+            Err(_) => unreachable!("上限がなければ部分集合構成法は中断しない"),
+        }
+    }
+
+    /// `to_nfa`と同じだが、作るNFAや、共通部分や補集合のために作るDFAが`limits`を超えたら中断する
+    pub fn to_nfa_with_limits(&self, env: &mut GlobalEnv, limits: Limits) -> Result<Nfa, Error> {
+        self.to_nfa_with_budget(env, &mut Budget::new(limits))
+    }
+
+    /// `to_nfa`と同じだが、共通部分や補集合のために作るDFAの状態と遷移の計算を`budget`で数え、
+    /// 上限を超えたら中断する
+    ///
+    /// Thompson構成で作るNFAの状態と遷移規則も、1つにつき遷移を1回計算したものとして数える。
+    /// 糖衣構文は1段ずつ展開するので、展開すると大きくなりすぎる正規表現も展開し終える前に中断できる。
+    pub fn to_nfa_with_budget(
+        &self,
+        env: &mut GlobalEnv,
+        budget: &mut Budget,
+    ) -> Result<Nfa, Error> {
+        let nfa = match self {
+            // 空集合: 開始状態から受理状態へ至る遷移がない
             RegExpr::Empty => {
+                budget.add_steps(2, 0)?;
                 let start = env.new_state();
                 let finish = env.new_state();
-                Nfa {
-                    start,
-                    finish,
-                    rules: vec![],
-                }
+                Nfa::new(start, finish, vec![])
+            }
+            // 空文字列: 開始状態からε遷移だけで受理状態へ至る
+            RegExpr::Epsilon => {
+                let start = env.new_state();
+                let finish = env.new_state();
+                let rules = vec![Rule {
+                    from: start,
+                    to: finish,
+                    label: Label::Epsilon,
+                }];
+                budget.add_steps(2 + rules.len(), 0)?;
+                Nfa::new(start, finish, rules)
             }
             RegExpr::Char(c) => {
                 let start = env.new_state();
@@ -48,40 +218,82 @@ impl RegExpr {
                 let rules = vec![Rule {
                     from: start,
                     to: finish,
-                    alphabet: *c,
+                    label: Label::Symbol(*c),
                 }];
-                Nfa {
-                    start,
-                    finish,
-                    rules,
-                }
+                budget.add_steps(2 + rules.len(), 0)?;
+                Nfa::new(start, finish, rules)
+            }
+            RegExpr::Class { negated, ranges } => {
+                let start = env.new_state();
+                let finish = env.new_state();
+                let alphabets: Vec<char> = if *negated {
+                    env.alphabets
+                        .iter()
+                        .filter(|c| !ranges.iter().any(|(a, b)| (a..=b).contains(c)))
+                        .copied()
+                        .collect()
+                } else {
+                    ranges.iter().flat_map(|(a, b)| *a..=*b).collect()
+                };
+                let rules: Vec<Rule> = alphabets
+                    .into_iter()
+                    .map(|c| Rule {
+                        from: start,
+                        to: finish,
+                        label: Label::Symbol(c),
+                    })
+                    .collect();
+                budget.add_steps(2 + rules.len(), 0)?;
+                Nfa::new(start, finish, rules)
+            }
+            RegExpr::Any => {
+                let start = env.new_state();
+                let finish = env.new_state();
+                let rules: Vec<Rule> = env
+                    .alphabets
+                    .iter()
+                    .map(|c| Rule {
+                        from: start,
+                        to: finish,
+                        label: Label::Symbol(*c),
+                    })
+                    .collect();
+                budget.add_steps(2 + rules.len(), 0)?;
+                Nfa::new(start, finish, rules)
             }
             RegExpr::Cat(v) => {
+                // 前後の状態と、部分式の間をつなぐv.len() + 1本のε遷移
+                budget.add_steps(2 + v.len() + 1, 0)?;
                 let start = env.new_state();
                 let finish = env.new_state();
                 let mut rules = vec![];
-                let mut nfa_vec: Vec<_> = v.iter().map(|r| r.to_nfa(env)).collect();
+                let mut nfa_vec = v
+                    .iter()
+                    .map(|r| r.to_nfa_with_budget(env, budget))
+                    .collect::<Result<Vec<_>, _>>()?;
 
-                if nfa_vec.len() == 0 {
-                    Nfa {
-                        start,
-                        finish,
-                        rules: vec![],
-                    }
+                if nfa_vec.is_empty() {
+                    // 何も連接しないものは空文字列を表す
+                    rules.push(Rule {
+                        from: start,
+                        to: finish,
+                        label: Label::Epsilon,
+                    });
+                    Nfa::new(start, finish, rules)
                 } else {
                     rules.push(Rule {
                         from: start,
                         to: nfa_vec[0].start,
-                        alphabet: 'ε',
+                        label: Label::Epsilon,
                     });
                     let mut i = 0;
                     while i < nfa_vec.len() {
-                        rules.append(&mut nfa_vec[i].rules);
+                        rules.append(&mut nfa_vec[i].take_rules());
                         if i < nfa_vec.len() - 1 {
                             rules.push(Rule {
                                 from: nfa_vec[i].finish,
                                 to: nfa_vec[i + 1].start,
-                                alphabet: 'ε',
+                                label: Label::Epsilon,
                             });
                         }
                         i += 1;
@@ -89,70 +301,120 @@ impl RegExpr {
                     rules.push(Rule {
                         from: nfa_vec[nfa_vec.len() - 1].finish,
                         to: finish,
-                        alphabet: 'ε',
+                        label: Label::Epsilon,
                     });
 
-                    Nfa {
-                        start,
-                        finish,
-                        rules,
-                    }
+                    Nfa::new(start, finish, rules)
                 }
             }
             RegExpr::Or(v) => {
+                // 前後の状態と、各部分式へ出入りする2本ずつのε遷移
+                budget.add_steps(2 + 2 * v.len(), 0)?;
                 let start = env.new_state();
                 let finish = env.new_state();
                 let mut rules = vec![];
-                for mut nfa in v.iter().map(|r| r.to_nfa(env)) {
+                let nfa_vec = v
+                    .iter()
+                    .map(|r| r.to_nfa_with_budget(env, budget))
+                    .collect::<Result<Vec<_>, _>>()?;
+                for mut nfa in nfa_vec {
                     rules.push(Rule {
                         from: start,
                         to: nfa.start,
-                        alphabet: 'ε',
+                        label: Label::Epsilon,
                     });
                     rules.push(Rule {
                         from: nfa.finish,
                         to: finish,
-                        alphabet: 'ε',
+                        label: Label::Epsilon,
                     });
-                    rules.append(&mut nfa.rules);
-                }
-                Nfa {
-                    start,
-                    finish,
-                    rules,
+                    rules.append(&mut nfa.take_rules());
                 }
+                Nfa::new(start, finish, rules)
             }
             RegExpr::Repeat(r) => {
+                // 新しい開始状態と、2本のε遷移
+                budget.add_steps(1 + 2, 0)?;
                 let start = env.new_state();
                 let mut rules = vec![];
 
-                let mut nfa = r.to_nfa(env);
-                rules.append(&mut nfa.rules);
+                let mut nfa = r.to_nfa_with_budget(env, budget)?;
+                rules.append(&mut nfa.take_rules());
 
                 rules.push(Rule {
                     from: start,
                     to: nfa.finish,
-                    alphabet: 'ε',
+                    label: Label::Epsilon,
                 });
                 rules.push(Rule {
                     from: nfa.finish,
                     to: nfa.start,
-                    alphabet: 'ε',
+                    label: Label::Epsilon,
                 });
-                Nfa {
-                    start,
-                    finish: nfa.finish,
-                    rules,
-                }
+                Nfa::new(start, nfa.finish, rules)
             }
-        }
+            RegExpr::And(v) => {
+                let alphabets = env.alphabets.clone();
+                let mut dfas = v
+                    .iter()
+                    .map(|r| r.to_dfa_with_budget(&alphabets, budget))
+                    .collect::<Result<Vec<_>, _>>()?
+                    .into_iter();
+                let dfa = match dfas.next() {
+                    Some(first) => dfas.try_fold(first, |a, b| {
+                        a.product_with_budget(&b, BoolOp::Intersection, budget)
+                            .map(|p| p.dfa)
+                    })?,
+                    None => RegExpr::Empty.to_dfa(&alphabets).complement(&alphabets),
+                };
+                Nfa::from_dfa(&dfa.minimize_with_budget(budget)?.dfa, env)
+            }
+            RegExpr::Not(r) => {
+                let alphabets = env.alphabets.clone();
+                let dfa = r
+                    .to_dfa_with_budget(&alphabets, budget)?
+                    .complement_with_budget(&alphabets, budget)?;
+                Nfa::from_dfa(&dfa.minimize_with_budget(budget)?.dfa, env)
+            }
+            RegExpr::Plus(_) | RegExpr::Optional(_) | RegExpr::Bounded { .. } => {
+                self.expand().to_nfa_with_budget(env, budget)?
+            }
+        };
+        Ok(nfa)
+    }
+
+    /// NFAを経由して、文字の集合`alphabets`上のDFAに変換する
+    pub fn to_dfa(&self, alphabets: &[char]) -> Dfa {
+        let mut env = GlobalEnv::new(alphabets);
+        self.to_nfa(&mut env).to_dfa(alphabets)
+    }
+
+    /// `to_dfa`と同じだが、途中で作るDFAも合わせて`limits`を超えたら中断する
+    pub fn to_dfa_with_limits(&self, alphabets: &[char], limits: Limits) -> Result<Dfa, Error> {
+        self.to_dfa_with_budget(alphabets, &mut Budget::new(limits))
+    }
+
+    /// `to_dfa`と同じだが、作ったDFAの状態と遷移の計算を`budget`で数え、上限を超えたら中断する
+    pub fn to_dfa_with_budget(
+        &self,
+        alphabets: &[char],
+        budget: &mut Budget,
+    ) -> Result<Dfa, Error> {
+        let mut env = GlobalEnv::new(alphabets);
+        let nfa = self.to_nfa_with_budget(&mut env, budget)?;
+        Ok(nfa.subset_construction_with_budget(alphabets, budget)?.0)
     }
 
     pub fn get_alphabets(&self) -> Vec<char> {
         let mut v = vec![];
         match self {
-            RegExpr::Empty => {}
+            RegExpr::Empty | RegExpr::Epsilon => {}
             RegExpr::Char(c) => v.push(*c),
+            // 否定の文字クラスでも、書かれた文字は考えている文字の集合に含める
+            RegExpr::Class { ranges, .. } => {
+                v.extend(ranges.iter().flat_map(|(a, b)| *a..=*b));
+            }
+            RegExpr::Any => {}
             RegExpr::Cat(rs) => {
                 for r in rs {
                     v.append(&mut r.get_alphabets());
@@ -166,40 +428,114 @@ impl RegExpr {
             RegExpr::Repeat(r) => {
                 v.append(&mut r.get_alphabets());
             }
+            RegExpr::And(rs) => {
+                for r in rs {
+                    v.append(&mut r.get_alphabets());
+                }
+            }
+            RegExpr::Not(r) | RegExpr::Plus(r) | RegExpr::Optional(r) => {
+                v.append(&mut r.get_alphabets());
+            }
+            RegExpr::Bounded { expr, .. } => {
+                v.append(&mut expr.get_alphabets());
+            }
         }
         v
     }
 }
 
+/// `{:#}`で表示すると、構造を示す括弧や演算子を薄い色で表示する
 impl Display for RegExpr {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        const T: &str = "\x1b[2m";
-        const R: &str = "\x1b[0m";
+        let alternate = f.alternate();
+        let (t, r) = if alternate {
+            ("\x1b[2m", "\x1b[0m")
+        } else {
+            ("", "")
+        };
+        let sub = |e: &RegExpr| {
+            if alternate {
+                format!("{:#}", e)
+            } else {
+                format!("{}", e)
+            }
+        };
         match self {
-            RegExpr::Empty => f.write_fmt(format_args!("{T}ε{R}"))?,
-            RegExpr::Char(c) => f.write_fmt(format_args!("{}", c))?,
+            RegExpr::Empty => f.write_str("φ")?,
+            RegExpr::Epsilon => f.write_str("ε")?,
+            RegExpr::Char(c) => f.write_str(&parser::escape(*c))?,
+            RegExpr::Class { negated, ranges } => {
+                f.write_str("[")?;
+                if *negated {
+                    f.write_str("^")?;
+                }
+                for (a, b) in ranges {
+                    f.write_str(&parser::escape_in_class(*a))?;
+                    if a != b {
+                        f.write_fmt(format_args!("-{}", parser::escape_in_class(*b)))?;
+                    }
+                }
+                f.write_str("]")?;
+            }
+            RegExpr::Any => f.write_str(".")?,
             RegExpr::Cat(v) => {
-                f.write_fmt(format_args!("{T}({R}"))?;
-                for r in v {
-                    f.write_fmt(format_args!("{}", r))?;
+                f.write_fmt(format_args!("{t}({r}"))?;
+                for e in v {
+                    f.write_str(&sub(e))?;
                 }
-                f.write_fmt(format_args!("{T}){R}"))?;
+                f.write_fmt(format_args!("{t}){r}"))?;
             }
             RegExpr::Or(v) => {
-                f.write_fmt(format_args!("{T}({R}"))?;
+                f.write_fmt(format_args!("{t}({r}"))?;
                 f.write_fmt(format_args!(
                     "{}",
                     v.iter()
-                        .map(|r| format!("{}", r))
+                        .map(sub)
                         .collect::<Vec<_>>()
-                        .join(&format!("{T}|{R}"))
+                        .join(&format!("{t}|{r}"))
                 ))?;
-                f.write_fmt(format_args!("{T}){R}"))?;
+                f.write_fmt(format_args!("{t}){r}"))?;
             }
-            RegExpr::Repeat(r) => {
-                f.write_fmt(format_args!("{T}({R}{r}{T})*{R}"))?;
+            RegExpr::Repeat(e) => {
+                f.write_fmt(format_args!("{t}({r}{}{t})*{r}", sub(e)))?;
+            }
+            RegExpr::And(v) => {
+                f.write_fmt(format_args!("{t}({r}"))?;
+                f.write_fmt(format_args!(
+                    "{}",
+                    v.iter()
+                        .map(sub)
+                        .collect::<Vec<_>>()
+                        .join(&format!("{t}&{r}"))
+                ))?;
+                f.write_fmt(format_args!("{t}){r}"))?;
+            }
+            RegExpr::Not(e) => {
+                f.write_fmt(format_args!("{t}~({r}{}{t}){r}", sub(e)))?;
+            }
+            RegExpr::Plus(e) => {
+                f.write_fmt(format_args!("{t}({r}{}{t})+{r}", sub(e)))?;
+            }
+            RegExpr::Optional(e) => {
+                f.write_fmt(format_args!("{t}({r}{}{t})?{r}", sub(e)))?;
+            }
+            RegExpr::Bounded { expr, min, max } => {
+                f.write_fmt(format_args!(
+                    "{t}({r}{}{t}){}{r}",
+                    sub(expr),
+                    fmt_bounds(*min, *max)
+                ))?;
             }
         }
         Ok(())
     }
 }
+
+/// 繰り返しの回数の指定を`{n}`、`{n,}`、`{n,m}`の形にする
+pub(crate) fn fmt_bounds(min: usize, max: Option<usize>) -> String {
+    match max {
+        Some(max) if max == min => format!("{{{}}}", min),
+        Some(max) => format!("{{{},{}}}", min, max),
+        None => format!("{{{},}}", min),
+    }
+}
@@ -0,0 +1,126 @@
+//! オートマトンや構文木を他のツールで表示できる形式に変換する
+
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    dfa::Dfa,
+    error::Error,
+    nfa::{Label, Nfa},
+    parser,
+    regexpr::RegExpr,
+};
+
+pub mod ascii;
+pub mod dot;
+pub mod latex;
+pub mod layout;
+pub mod mermaid;
+pub mod plantuml;
+pub mod svg;
+pub mod tikz;
+
+/// 出力形式
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Format {
+    /// Graphvizのdot形式
+    Dot,
+    /// Mermaidの状態遷移図
+    Mermaid,
+    /// PlantUMLの状態遷移図
+    #[value(name = "plantuml")]
+    PlantUml,
+    /// TikZの状態遷移図
+    Tikz,
+    /// DFAの状態遷移表(LaTeXのtabular)
+    LatexTable,
+    /// 自動で配置したSVG画像
+    Svg,
+    /// 端末で表示するための罫線文字による図。大きすぎるときは遷移規則の一覧
+    Ascii,
+}
+
+/// 出力する図
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Graph {
+    /// 正規表現の構文木
+    Ast,
+    Nfa,
+    Dfa,
+    /// 最小化したDFA
+    MinDfa,
+}
+
+/// 正規表現とそれから作ったNFA、DFAのうち、`graph`で選んだものを`format`の形式で出力する
+pub fn render(
+    regexpr: &RegExpr,
+    nfa: &Nfa,
+    dfa: &Dfa,
+    graph: Graph,
+    format: Format,
+) -> Result<String, Error> {
+    let s = match (format, graph) {
+        (Format::Dot, Graph::Ast) => dot::regexpr(regexpr),
+        (Format::Dot, Graph::Nfa) => dot::nfa(nfa),
+        (Format::Dot, Graph::Dfa) => dot::dfa(dfa),
+        (Format::Dot, Graph::MinDfa) => dot::dfa(&dfa.minimize().dfa),
+        (Format::Mermaid, Graph::Nfa) => mermaid::nfa(nfa),
+        (Format::Mermaid, Graph::Dfa) => mermaid::dfa(dfa),
+        (Format::Mermaid, Graph::MinDfa) => mermaid::dfa(&dfa.minimize().dfa),
+        (Format::PlantUml, Graph::Nfa) => plantuml::nfa(nfa),
+        (Format::PlantUml, Graph::Dfa) => plantuml::dfa(dfa),
+        (Format::PlantUml, Graph::MinDfa) => plantuml::dfa(&dfa.minimize().dfa),
+        (Format::Tikz, Graph::Nfa) => tikz::nfa(nfa),
+        (Format::Tikz, Graph::Dfa) => tikz::dfa(dfa),
+        (Format::Tikz, Graph::MinDfa) => tikz::dfa(&dfa.minimize().dfa),
+        (Format::Svg, Graph::Nfa) => svg::nfa(nfa),
+        (Format::Svg, Graph::Dfa) => svg::dfa(dfa),
+        (Format::Svg, Graph::MinDfa) => svg::dfa(&dfa.minimize().dfa),
+        (Format::Ascii, Graph::Nfa) => ascii::nfa(nfa),
+        (Format::Ascii, Graph::Dfa) => ascii::dfa(dfa),
+        (Format::Ascii, Graph::MinDfa) => ascii::dfa(&dfa.minimize().dfa),
+        (Format::LatexTable, Graph::Dfa) => latex::table(&dfa.to_table()),
+        (Format::LatexTable, Graph::MinDfa) => latex::table(&dfa.minimize().dfa.to_table()),
+        (format, graph) => return Err(Error::UnsupportedGraph { format, graph }),
+    };
+    Ok(s)
+}
+
+/// 遷移規則の文字の一覧を表示用に`a,b,c`の形式でつなげる
+pub(crate) fn join_alphabets(alphabets: &[char]) -> String {
+    alphabets
+        .iter()
+        .map(|c| display_char(*c))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// 遷移規則の文字を表示用にする。空白や制御文字は見える形にする
+pub(crate) fn display_char(c: char) -> String {
+    match c {
+        ' ' => "␣".to_string(),
+        '\n' => "\\n".to_string(),
+        '\t' => "\\t".to_string(),
+        c if c.is_control() || c.is_whitespace() => format!("\\u{{{:x}}}", c as u32),
+        c => c.to_string(),
+    }
+}
+
+/// NFAの遷移規則のラベルを表示用にする。文字の`ε`はε遷移と区別できるように`\ε`にする
+pub(crate) fn display_label(label: Label) -> String {
+    match label {
+        Label::Epsilon => "ε".to_string(),
+        Label::Symbol('ε') => parser::escape('ε'),
+        Label::Symbol(c) => display_char(c),
+    }
+}
+
+/// NFAの遷移規則のラベルの一覧を表示用に`a,b,c`の形式でつなげる
+pub(crate) fn join_labels(labels: &[Label]) -> String {
+    labels
+        .iter()
+        .map(|l| display_label(*l))
+        .collect::<Vec<_>>()
+        .join(",")
+}
@@ -0,0 +1,374 @@
+//! 端末で表示するための罫線文字による状態遷移図
+//!
+//! 状態を箱にして`layout`の順に横一列に並べ、隣の状態への遷移は箱の間の矢印で、
+//! それ以外の遷移は右向きなら箱の上、左向きなら箱の下を通る矢印で描く。
+//! 状態が多すぎたり幅が広すぎたりして端末に収まらないときは、遷移規則の一覧を返す。
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{
+    dfa::{Dfa, State},
+    diagnostic::{char_width, width},
+    nfa::Nfa,
+};
+
+use super::{
+    join_alphabets, join_labels,
+    layout::{self, Edge, Layout},
+};
+
+/// 図にする状態の数の上限
+const MAX_STATES: usize = 16;
+/// 図の幅の上限
+const MAX_WIDTH: usize = 120;
+
+const UP: u8 = 1;
+const DOWN: u8 = 2;
+const LEFT: u8 = 4;
+const RIGHT: u8 = 8;
+
+pub fn dfa(dfa: &Dfa) -> String {
+    let edges = layout::dfa_edges(dfa, join_alphabets);
+    picture(dfa.start, &dfa.finish_states, &dfa.states(), edges)
+        .unwrap_or_else(|| format!("{}\n", dfa))
+}
+
+pub fn nfa(nfa: &Nfa) -> String {
+    let edges = layout::nfa_edges(nfa, join_labels);
+    let finish = BTreeSet::from([nfa.finish]);
+    picture(nfa.start, &finish, &nfa.states(), edges).unwrap_or_else(|| format!("{}\n", nfa))
+}
+
+#[derive(Clone, Copy)]
+enum Cell {
+    Empty,
+    /// 罫線。つながる向きの組み合わせ
+    Line(u8),
+    Char(char),
+    /// 直前の全角文字が占めている
+    Wide,
+}
+
+struct Canvas {
+    cells: Vec<Vec<Cell>>,
+}
+
+impl Canvas {
+    fn new(width: usize, height: usize) -> Self {
+        Self {
+            cells: vec![vec![Cell::Empty; width]; height],
+        }
+    }
+
+    fn line(&mut self, row: usize, col: usize, directions: u8) {
+        let cell = &mut self.cells[row][col];
+        *cell = match *cell {
+            Cell::Line(d) => Cell::Line(d | directions),
+            _ => Cell::Line(directions),
+        };
+    }
+
+    /// `row`行の`from`列から`to`列まで横線を引く
+    fn horizontal(&mut self, row: usize, from: usize, to: usize) {
+        for col in from..=to {
+            let mut d = 0;
+            if col > from {
+                d |= LEFT;
+            }
+            if col < to {
+                d |= RIGHT;
+            }
+            self.line(row, col, d);
+        }
+    }
+
+    /// `col`列の`from`行から`to`行まで縦線を引く
+    fn vertical(&mut self, col: usize, from: usize, to: usize) {
+        for row in from..=to {
+            let mut d = 0;
+            if row > from {
+                d |= UP;
+            }
+            if row < to {
+                d |= DOWN;
+            }
+            self.line(row, col, d);
+        }
+    }
+
+    /// `row`行の`col`列から`s`を書く。キャンバスからはみ出す文字は書かない
+    fn text(&mut self, row: usize, col: usize, s: &str) {
+        let cells = &mut self.cells[row];
+        let mut col = col;
+        for c in s.chars() {
+            let w = char_width(c);
+            if col + w > cells.len() {
+                break;
+            }
+            cells[col] = Cell::Char(c);
+            if w == 2 {
+                cells[col + 1] = Cell::Wide;
+            }
+            col += w;
+        }
+    }
+
+    /// `row`行の`from`列から`to`列の間に縦線と交わらないように`s`を書く。
+    /// 交わらない位置がなければ、できるだけ中央に書く
+    fn label(&mut self, row: usize, from: usize, to: usize, s: &str) {
+        let w = width(s);
+        let center = (from + to + 1).saturating_sub(w) / 2;
+        let mut candidates: Vec<usize> = (from..=(to + 1).saturating_sub(w).max(from)).collect();
+        candidates.sort_by_key(|col| col.abs_diff(center));
+        let col = candidates
+            .iter()
+            .find(|col| {
+                self.cells[row][**col..(**col + w).min(self.cells[row].len())]
+                    .iter()
+                    .all(|cell| !matches!(cell, Cell::Line(d) if d & (UP | DOWN) != 0))
+            })
+            .copied()
+            .unwrap_or(center.max(from));
+        self.text(row, col, s);
+    }
+
+    fn render(&self) -> String {
+        let mut s = String::new();
+        for row in &self.cells {
+            let mut line = String::new();
+            for cell in row {
+                match cell {
+                    Cell::Empty => line.push(' '),
+                    Cell::Line(d) => line.push(line_char(*d)),
+                    Cell::Char(c) => line.push(*c),
+                    Cell::Wide => {}
+                }
+            }
+            s.push_str(line.trim_end());
+            s.push('\n');
+        }
+        s
+    }
+}
+
+fn line_char(directions: u8) -> char {
+    match directions {
+        d if d == UP | DOWN => '│',
+        d if d == LEFT | RIGHT => '─',
+        d if d == DOWN | RIGHT => '┌',
+        d if d == DOWN | LEFT => '┐',
+        d if d == UP | RIGHT => '└',
+        d if d == UP | LEFT => '┘',
+        d if d == UP | DOWN | RIGHT => '├',
+        d if d == UP | DOWN | LEFT => '┤',
+        d if d == LEFT | RIGHT | DOWN => '┬',
+        d if d == LEFT | RIGHT | UP => '┴',
+        d if d == UP | DOWN | LEFT | RIGHT => '┼',
+        d if d & (UP | DOWN) != 0 => '│',
+        _ => '─',
+    }
+}
+
+/// 箱の上または下を通る遷移
+struct Arc {
+    edge: usize,
+    /// 箱の上を通るかどうか
+    above: bool,
+    /// 遷移元の箱の端の列
+    from_col: usize,
+    /// 遷移先の箱の端の列
+    to_col: usize,
+    /// 箱から何番目の横線を通るか
+    lane: usize,
+}
+
+fn picture(
+    start: State,
+    finish_states: &BTreeSet<State>,
+    states: &BTreeSet<State>,
+    edges: Vec<Edge>,
+) -> Option<String> {
+    if states.len() > MAX_STATES {
+        return None;
+    }
+    let layout = Layout::new(start, states, &edges);
+    let order: Vec<State> = layout.layers.iter().flatten().copied().collect();
+    let index: BTreeMap<State, usize> = order.iter().enumerate().map(|(i, s)| (*s, i)).collect();
+
+    // 箱の上下の端点を、つながる相手の箱の順に並べる。外側を通る矢印ほど端の点を使うので、矢印が交差しない。
+    // 自己遷移は(相手の箱, 0: 遷移元 / 1: 遷移先)の順で隣り合う
+    let mut ports: Vec<[Vec<(usize, u8, usize)>; 2]> = vec![[vec![], vec![]]; order.len()];
+    let mut inline = BTreeMap::new();
+    let mut arcs = vec![];
+    for (i, edge) in edges.iter().enumerate() {
+        let from = index[&edge.from];
+        let to = index[&edge.to];
+        if to == from + 1 {
+            inline.insert(from, i);
+            continue;
+        }
+        let above = to >= from;
+        let side = if above { 0 } else { 1 };
+        ports[from][side].push((to, 0, i));
+        ports[to][side].push((from, 1, i));
+        arcs.push(Arc {
+            edge: i,
+            above,
+            from_col: 0,
+            to_col: 0,
+            lane: 0,
+        });
+    }
+
+    // 箱の中での端点の位置(箱の内側の左端から)と、端点を並べるのに必要な幅
+    let mut offsets: BTreeMap<(usize, usize, u8), usize> = BTreeMap::new();
+    let mut inner_widths = vec![];
+    for (i, state) in order.iter().enumerate() {
+        let mut used = [0, 0];
+        for side in 0..2 {
+            ports[i][side].sort();
+            let mut cursor = 1;
+            for (other, end, edge) in &ports[i][side] {
+                if *other == i && *end == 1 {
+                    continue;
+                }
+                offsets.insert((i, *edge, *end), cursor);
+                if *other == i {
+                    let w = width(&edges[*edge].label);
+                    offsets.insert((i, *edge, 1), cursor + w + 1);
+                    cursor += w + 3;
+                } else {
+                    cursor += 2;
+                }
+            }
+            used[side] = cursor;
+        }
+        let inner = (width(&state.to_string()) + 2).max(used[0]).max(used[1]);
+        // 端点をまとめて箱の中央に寄せる
+        for side in 0..2 {
+            let shift = (inner - used[side]) / 2;
+            for (_, end, edge) in &ports[i][side] {
+                if let Some(offset) = offsets.get_mut(&(i, *edge, *end)) {
+                    *offset += shift;
+                }
+            }
+        }
+        inner_widths.push(inner);
+    }
+
+    // 箱の間隔は、隣への矢印にも上下を通る矢印にもラベルが収まるようにする
+    let gap = edges
+        .iter()
+        .filter(|e| e.from != e.to)
+        .map(|e| width(&e.label))
+        .max()
+        .unwrap_or(0)
+        + 4;
+    let mut xs = vec![];
+    let mut x = 3;
+    for inner in &inner_widths {
+        xs.push(x);
+        x += inner + 2 + gap;
+    }
+    let total_width = x - gap;
+    if total_width > MAX_WIDTH {
+        return None;
+    }
+
+    for arc in &mut arcs {
+        let edge = &edges[arc.edge];
+        let from = index[&edge.from];
+        let to = index[&edge.to];
+        arc.from_col = xs[from] + offsets[&(from, arc.edge, 0)];
+        arc.to_col = xs[to] + offsets[&(to, arc.edge, 1)];
+    }
+    // 短い矢印から順に、他の矢印と重ならない最も内側の横線に割り当てる
+    let mut lanes: [Vec<Vec<(usize, usize)>>; 2] = [vec![], vec![]];
+    let mut sorted: Vec<usize> = (0..arcs.len()).collect();
+    sorted.sort_by_key(|i| arcs[*i].from_col.abs_diff(arcs[*i].to_col));
+    for i in sorted {
+        let arc = &mut arcs[i];
+        let (l, r) = (arc.from_col.min(arc.to_col), arc.from_col.max(arc.to_col));
+        let lanes = &mut lanes[if arc.above { 0 } else { 1 }];
+        let lane = lanes
+            .iter()
+            .position(|lane| lane.iter().all(|(a, b)| r + 1 < *a || *b + 1 < l))
+            .unwrap_or(lanes.len());
+        if lane == lanes.len() {
+            lanes.push(vec![]);
+        }
+        lanes[lane].push((l, r));
+        arc.lane = lane;
+    }
+
+    // 上の横線、上の矢印の先、箱(3行)、下の矢印の先、下の横線の順に並べる
+    let above = lanes[0].len();
+    let below = lanes[1].len();
+    let top = above + 1;
+    let middle = top + 1;
+    let bottom = top + 2;
+    let mut canvas = Canvas::new(total_width, above + below + 5);
+
+    for arc in &arcs {
+        let (row, stub) = if arc.above {
+            (above - 1 - arc.lane, top - 1)
+        } else {
+            (bottom + 2 + arc.lane, bottom + 1)
+        };
+        let (l, r) = (arc.from_col.min(arc.to_col), arc.from_col.max(arc.to_col));
+        canvas.horizontal(row, l, r);
+        canvas.vertical(arc.from_col, row.min(stub), row.max(stub));
+        canvas.vertical(arc.to_col, row.min(stub), row.max(stub));
+        if arc.above {
+            canvas.line(stub, arc.from_col, DOWN);
+            canvas.text(stub, arc.to_col, "▼");
+        } else {
+            canvas.line(stub, arc.from_col, UP);
+            canvas.text(stub, arc.to_col, "▲");
+        }
+    }
+    for arc in &arcs {
+        let row = if arc.above {
+            above - 1 - arc.lane
+        } else {
+            bottom + 2 + arc.lane
+        };
+        let (l, r) = (arc.from_col.min(arc.to_col), arc.from_col.max(arc.to_col));
+        canvas.label(row, l + 1, r - 1, &edges[arc.edge].label);
+    }
+
+    canvas.text(middle, 0, "──▶");
+    for (i, state) in order.iter().enumerate() {
+        let x = xs[i];
+        let inner = inner_widths[i];
+        let [h, v, tl, tr, bl, br, up, down] = if finish_states.contains(state) {
+            ['═', '║', '╔', '╗', '╚', '╝', '╧', '╤']
+        } else {
+            ['─', '│', '┌', '┐', '└', '┘', '┴', '┬']
+        };
+        let h = h.to_string().repeat(inner);
+        canvas.text(top, x, &format!("{tl}{h}{tr}"));
+        canvas.text(bottom, x, &format!("{bl}{h}{br}"));
+        canvas.text(middle, x, &v.to_string());
+        canvas.text(middle, x + inner + 1, &v.to_string());
+        let name = state.to_string();
+        canvas.text(middle, x + 1 + (inner - width(&name)) / 2, &name);
+        for arc in &arcs {
+            let edge = &edges[arc.edge];
+            let (row, port) = if arc.above { (top, up) } else { (bottom, down) };
+            if edge.from == *state {
+                canvas.text(row, arc.from_col, &port.to_string());
+            }
+        }
+
+        if let Some(edge) = inline.get(&i) {
+            let from = x + inner + 2;
+            let to = xs[i + 1] - 1;
+            canvas.horizontal(middle, from, to - 1);
+            canvas.text(middle, to, "▶");
+            canvas.label(middle, from + 1, to - 2, &edges[*edge].label);
+        }
+    }
+
+    Some(canvas.render())
+}
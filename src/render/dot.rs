@@ -0,0 +1,109 @@
+//! Graphvizのdot形式
+
+use crate::{
+    dfa::Dfa,
+    nfa::{Label, Nfa},
+    parser,
+    regexpr::{fmt_bounds, RegExpr},
+};
+
+use super::{display_label, join_alphabets};
+
+/// dotの文字列リテラルの中身として使えるようにエスケープする
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+pub fn dfa(dfa: &Dfa) -> String {
+    let mut s = String::new();
+    s.push_str("digraph DFA {\n");
+    s.push_str("    rankdir=LR;\n");
+    s.push_str("    node [shape=circle];\n");
+    s.push_str("    __start [shape=point];\n");
+    s.push_str(&format!("    __start -> {};\n", dfa.start));
+    for state in &dfa.finish_states {
+        s.push_str(&format!("    {} [shape=doublecircle];\n", state));
+    }
+    for (from, to, alphabets) in dfa.grouped_rules() {
+        s.push_str(&format!(
+            "    {} -> {} [label=\"{}\"];\n",
+            from,
+            to,
+            escape(&join_alphabets(&alphabets))
+        ));
+    }
+    s.push_str("}\n");
+    s
+}
+
+pub fn nfa(nfa: &Nfa) -> String {
+    let mut s = String::new();
+    s.push_str("digraph NFA {\n");
+    s.push_str("    rankdir=LR;\n");
+    s.push_str("    node [shape=circle];\n");
+    s.push_str("    __start [shape=point];\n");
+    s.push_str(&format!("    __start -> {};\n", nfa.start));
+    s.push_str(&format!("    {} [shape=doublecircle];\n", nfa.finish));
+    for rule in nfa.rules() {
+        if rule.label == Label::Epsilon {
+            s.push_str(&format!(
+                "    {} -> {} [label=\"ε\", style=dashed, color=gray40];\n",
+                rule.from, rule.to
+            ));
+        } else {
+            s.push_str(&format!(
+                "    {} -> {} [label=\"{}\"];\n",
+                rule.from,
+                rule.to,
+                escape(&display_label(rule.label))
+            ));
+        }
+    }
+    s.push_str("}\n");
+    s
+}
+
+/// 正規表現の構文木
+pub fn regexpr(regexpr: &RegExpr) -> String {
+    let mut s = String::new();
+    s.push_str("digraph AST {\n");
+    s.push_str("    node [shape=circle];\n");
+    let mut count = 0;
+    regexpr_node(regexpr, &mut count, &mut s);
+    s.push_str("}\n");
+    s
+}
+
+/// 構文木のノードを出力し、そのノードの番号を返す
+fn regexpr_node(regexpr: &RegExpr, count: &mut usize, s: &mut String) -> usize {
+    let id = *count;
+    *count += 1;
+    let (label, children): (String, Vec<&RegExpr>) = match regexpr {
+        RegExpr::Empty => ("φ".to_string(), vec![]),
+        RegExpr::Epsilon => ("ε".to_string(), vec![]),
+        RegExpr::Char(c) => (parser::escape(*c), vec![]),
+        RegExpr::Class { .. } | RegExpr::Any => (regexpr.to_string(), vec![]),
+        RegExpr::Cat(v) => ("·".to_string(), v.iter().collect()),
+        RegExpr::Or(v) => ("|".to_string(), v.iter().collect()),
+        RegExpr::Repeat(r) => ("*".to_string(), vec![r]),
+        RegExpr::And(v) => ("&".to_string(), v.iter().collect()),
+        RegExpr::Not(r) => ("~".to_string(), vec![r]),
+        RegExpr::Plus(r) => ("+".to_string(), vec![r]),
+        RegExpr::Optional(r) => ("?".to_string(), vec![r]),
+        RegExpr::Bounded { expr, min, max } => (fmt_bounds(*min, *max), vec![expr]),
+    };
+    if children.is_empty() {
+        s.push_str(&format!(
+            "    n{} [label=\"{}\", shape=box];\n",
+            id,
+            escape(&label)
+        ));
+    } else {
+        s.push_str(&format!("    n{} [label=\"{}\"];\n", id, escape(&label)));
+    }
+    for child in children {
+        let child = regexpr_node(child, count, s);
+        s.push_str(&format!("    n{} -> n{};\n", id, child));
+    }
+    id
+}
@@ -0,0 +1,65 @@
+//! LaTeXの表
+//!
+//! 記号でない文字を`\text`で書くので、`\usepackage{amsmath}`が必要。
+
+use std::collections::BTreeSet;
+
+use crate::dfa::Table;
+
+/// 文字をLaTeXの数式モードの中で使える形にする
+pub(crate) fn math(c: char) -> String {
+    match c {
+        'φ' => "\\phi".to_string(),
+        '\\' => "\\backslash".to_string(),
+        '^' => "\\hat{}".to_string(),
+        '~' => "\\sim".to_string(),
+        '#' | '$' | '%' | '&' | '_' | '{' | '}' => format!("\\{}", c),
+        ' ' => "\\text{\\textvisiblespace}".to_string(),
+        // ε遷移の\varepsilonと区別できるように、文字のεは\εと表示する
+        'ε' => "\\text{\\textbackslash ε}".to_string(),
+        c if c.is_control() || c.is_whitespace() => format!("\\text{{U+{:04X}}}", c as u32),
+        c if c.is_ascii() => c.to_string(),
+        c => format!("\\text{{{}}}", c),
+    }
+}
+
+/// 状態遷移表を`tabular`環境にする。
+/// 開始状態には`$\rightarrow$`を、受理状態には`$*$`を付け、遷移規則がないところは`$-$`にする
+pub fn table(table: &Table) -> String {
+    let alphabets: BTreeSet<char> = table
+        .values()
+        .flat_map(|(_, _, row)| row.keys().copied())
+        .collect();
+
+    let mut s = String::new();
+    s.push_str(&format!(
+        "\\begin{{tabular}}{{r|{}}}\n",
+        "c".repeat(alphabets.len())
+    ));
+    for c in &alphabets {
+        s.push_str(&format!(" & ${}$", math(*c)));
+    }
+    s.push_str(" \\\\\n\\hline\n");
+
+    let starts = table.iter().filter(|(_, (_, is_start, _))| *is_start);
+    let others = table.iter().filter(|(_, (_, is_start, _))| !*is_start);
+    for (state, (is_finish, is_start, row)) in starts.chain(others) {
+        let mut marks = String::new();
+        if *is_start {
+            marks.push_str("\\rightarrow ");
+        }
+        if *is_finish {
+            marks.push_str("* ");
+        }
+        s.push_str(&format!("${}{}$", marks, state));
+        for c in &alphabets {
+            match row.get(c) {
+                Some(to) => s.push_str(&format!(" & ${}$", to)),
+                None => s.push_str(" & $-$"),
+            }
+        }
+        s.push_str(" \\\\\n");
+    }
+    s.push_str("\\end{tabular}\n");
+    s
+}
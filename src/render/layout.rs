@@ -0,0 +1,188 @@
+//! 状態遷移図の自動配置
+//!
+//! 開始状態からの最短距離で状態を層に分け、層の中の順番は隣接する層での位置の平均(重心法)で並べ替えて
+//! 遷移の交差を減らす。
+
+use std::collections::{BTreeMap, BTreeSet, HashSet, VecDeque};
+
+use crate::{
+    dfa::{Dfa, State},
+    nfa::{Label, Nfa},
+};
+
+/// 重心法で並べ替えを繰り返す回数
+const SWEEPS: usize = 4;
+
+/// 図に描く遷移。同じ状態の間の遷移はまとめて1本にする
+pub struct Edge {
+    pub from: State,
+    pub to: State,
+    pub label: String,
+    /// ε遷移
+    pub dashed: bool,
+}
+
+/// DFAの遷移規則を遷移元と遷移先ごとにまとめる。`label`で文字の一覧をラベルにする
+pub fn dfa_edges(dfa: &Dfa, label: impl Fn(&[char]) -> String) -> Vec<Edge> {
+    dfa.grouped_rules()
+        .into_iter()
+        .map(|(from, to, alphabets)| Edge {
+            from,
+            to,
+            label: label(&alphabets),
+            dashed: false,
+        })
+        .collect()
+}
+
+/// NFAの遷移規則を遷移元と遷移先ごとにまとめる。`label`でラベルの一覧をラベルにする。
+/// まとめた遷移がすべてε遷移なら破線にする
+pub fn nfa_edges(nfa: &Nfa, label: impl Fn(&[Label]) -> String) -> Vec<Edge> {
+    let mut grouped: BTreeMap<(State, State), Vec<Label>> = BTreeMap::new();
+    for rule in nfa.rules() {
+        grouped
+            .entry((rule.from, rule.to))
+            .or_default()
+            .push(rule.label);
+    }
+    grouped
+        .into_iter()
+        .map(|((from, to), labels)| Edge {
+            from,
+            to,
+            label: label(&labels),
+            dashed: labels.iter().all(|l| *l == Label::Epsilon),
+        })
+        .collect()
+}
+
+#[derive(Debug)]
+pub struct Layout {
+    /// 層ごとの状態の並び
+    pub layers: Vec<Vec<State>>,
+    /// 状態 => (層, 層の中での順番)
+    pub positions: BTreeMap<State, (usize, usize)>,
+    /// 遷移がある(遷移元, 遷移先)の組
+    pairs: HashSet<(State, State)>,
+}
+
+impl Layout {
+    /// 開始状態`start`から遷移`edges`を辿って`states`を配置する。
+    /// 開始状態から到達できない状態は最後の層に置く
+    pub fn new(start: State, states: &BTreeSet<State>, edges: &[Edge]) -> Self {
+        let mut neighbors: BTreeMap<State, BTreeSet<State>> = BTreeMap::new();
+        let mut successors: BTreeMap<State, BTreeSet<State>> = BTreeMap::new();
+        for Edge { from, to, .. } in edges {
+            if from != to {
+                neighbors.entry(*from).or_default().insert(*to);
+                neighbors.entry(*to).or_default().insert(*from);
+                successors.entry(*from).or_default().insert(*to);
+            }
+        }
+
+        let mut depth = BTreeMap::new();
+        let mut queue = VecDeque::new();
+        depth.insert(start, 0);
+        queue.push_back(start);
+        while let Some(s) = queue.pop_front() {
+            for t in successors.get(&s).into_iter().flatten() {
+                if !depth.contains_key(t) {
+                    depth.insert(*t, depth[&s] + 1);
+                    queue.push_back(*t);
+                }
+            }
+        }
+        let unreachable = depth.values().max().map_or(0, |d| d + 1);
+
+        let mut layers: Vec<Vec<State>> = vec![];
+        for s in states {
+            let d = *depth.get(s).unwrap_or(&unreachable);
+            if layers.len() <= d {
+                layers.resize(d + 1, vec![]);
+            }
+            layers[d].push(*s);
+        }
+        layers.retain(|layer| !layer.is_empty());
+
+        let mut layout = Self {
+            layers,
+            positions: BTreeMap::new(),
+            pairs: edges.iter().map(|e| (e.from, e.to)).collect(),
+        };
+        layout.update_positions();
+        for _ in 0..SWEEPS {
+            for i in 1..layout.layers.len() {
+                layout.reorder(i, i - 1, &neighbors);
+            }
+            for i in (0..layout.layers.len().saturating_sub(1)).rev() {
+                layout.reorder(i, i + 1, &neighbors);
+            }
+        }
+        layout
+    }
+
+    /// 層`layer`の状態を、層`fixed`にある隣接状態の位置の平均の順に並べ替える。
+    /// 層`fixed`に隣接状態がない状態は今の位置を保つ
+    fn reorder(
+        &mut self,
+        layer: usize,
+        fixed: usize,
+        neighbors: &BTreeMap<State, BTreeSet<State>>,
+    ) {
+        let mut keyed: Vec<(f64, State)> = self.layers[layer]
+            .iter()
+            .enumerate()
+            .map(|(i, s)| {
+                let adjacent: Vec<usize> = neighbors
+                    .get(s)
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|t| self.positions.get(t))
+                    .filter(|(l, _)| *l == fixed)
+                    .map(|(_, index)| *index)
+                    .collect();
+                let key = if adjacent.is_empty() {
+                    i as f64
+                } else {
+                    adjacent.iter().sum::<usize>() as f64 / adjacent.len() as f64
+                };
+                (key, *s)
+            })
+            .collect();
+        keyed.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)));
+        self.layers[layer] = keyed.into_iter().map(|(_, s)| s).collect();
+        self.update_positions();
+    }
+
+    fn update_positions(&mut self) {
+        self.positions.clear();
+        for (l, layer) in self.layers.iter().enumerate() {
+            for (i, s) in layer.iter().enumerate() {
+                self.positions.insert(*s, (l, i));
+            }
+        }
+    }
+
+    /// 層`layer`の`index`番目の、層の中心からのずれ。層の中の状態は上下対称に並べる
+    pub fn offset(&self, layer: usize, index: usize) -> f64 {
+        index as f64 - (self.layers[layer].len() as f64 - 1.0) / 2.0
+    }
+
+    /// 状態`from`から`to`への矢印を進行方向の左に曲げる度合い
+    ///
+    /// 隣の層へ進む遷移は曲げずに0を返す。逆向きの遷移もある場合と、同じ層や前の層への遷移は
+    /// 他の矢印と重ならないように1を返す。2つ以上離れた層への遷移は、状態と重ならないように層の差を返す。
+    /// 自己遷移では使わない
+    pub fn bend(&self, from: State, to: State) -> usize {
+        let (from_layer, _) = self.positions[&from];
+        let (to_layer, _) = self.positions[&to];
+        let span = from_layer.abs_diff(to_layer);
+        if span > 1 {
+            span
+        } else if self.pairs.contains(&(to, from)) || to_layer != from_layer + 1 {
+            1
+        } else {
+            0
+        }
+    }
+}
@@ -0,0 +1,71 @@
+//! Mermaidの状態遷移図(stateDiagram-v2)
+
+use crate::{dfa::Dfa, nfa::Nfa};
+
+use super::{display_label, join_alphabets};
+
+/// 遷移のラベルとして使えるように、Mermaidの構文と紛らわしい文字を`#コード;`の形式にする
+fn escape(s: &str) -> String {
+    s.chars()
+        .map(|c| {
+            if "\"#%:;<>[]{}".contains(c) {
+                format!("#{};", c as u32)
+            } else {
+                c.to_string()
+            }
+        })
+        .collect()
+}
+
+fn header(s: &mut String) {
+    s.push_str("stateDiagram-v2\n");
+    s.push_str("    direction LR\n");
+    s.push_str("    classDef accepting stroke-width:4px\n");
+}
+
+fn state(s: &mut String, state: i32, accepting: bool) {
+    s.push_str(&format!("    state \"{}\" as s{}\n", state, state));
+    if accepting {
+        s.push_str(&format!("    class s{} accepting\n", state));
+    }
+}
+
+pub fn dfa(dfa: &Dfa) -> String {
+    let mut s = String::new();
+    header(&mut s);
+    for q in dfa.states() {
+        state(&mut s, q, dfa.finish_states.contains(&q));
+    }
+    s.push_str(&format!("    [*] --> s{}\n", dfa.start));
+    for (from, to, alphabets) in dfa.grouped_rules() {
+        s.push_str(&format!(
+            "    s{} --> s{}: {}\n",
+            from,
+            to,
+            escape(&join_alphabets(&alphabets))
+        ));
+    }
+    for q in &dfa.finish_states {
+        s.push_str(&format!("    s{} --> [*]\n", q));
+    }
+    s
+}
+
+pub fn nfa(nfa: &Nfa) -> String {
+    let mut s = String::new();
+    header(&mut s);
+    for q in nfa.states() {
+        state(&mut s, q, q == nfa.finish);
+    }
+    s.push_str(&format!("    [*] --> s{}\n", nfa.start));
+    for rule in nfa.rules() {
+        s.push_str(&format!(
+            "    s{} --> s{}: {}\n",
+            rule.from,
+            rule.to,
+            escape(&display_label(rule.label))
+        ));
+    }
+    s.push_str(&format!("    s{} --> [*]\n", nfa.finish));
+    s
+}
@@ -0,0 +1,70 @@
+//! PlantUMLの状態遷移図
+
+use crate::{
+    dfa::Dfa,
+    nfa::{Label, Nfa},
+};
+
+use super::{display_label, join_alphabets};
+
+fn header(s: &mut String) {
+    s.push_str("@startuml\n");
+    s.push_str("hide empty description\n");
+    s.push_str("left to right direction\n");
+}
+
+/// 受理状態は太い枠線で描く
+fn state(s: &mut String, state: i32, accepting: bool) {
+    if accepting {
+        s.push_str(&format!("state \"{}\" as s{} ##[bold]\n", state, state));
+    } else {
+        s.push_str(&format!("state \"{}\" as s{}\n", state, state));
+    }
+}
+
+pub fn dfa(dfa: &Dfa) -> String {
+    let mut s = String::new();
+    header(&mut s);
+    for q in dfa.states() {
+        state(&mut s, q, dfa.finish_states.contains(&q));
+    }
+    s.push_str(&format!("[*] --> s{}\n", dfa.start));
+    for (from, to, alphabets) in dfa.grouped_rules() {
+        s.push_str(&format!(
+            "s{} --> s{} : {}\n",
+            from,
+            to,
+            join_alphabets(&alphabets)
+        ));
+    }
+    for q in &dfa.finish_states {
+        s.push_str(&format!("s{} --> [*]\n", q));
+    }
+    s.push_str("@enduml\n");
+    s
+}
+
+/// ε遷移は破線の矢印で描く
+pub fn nfa(nfa: &Nfa) -> String {
+    let mut s = String::new();
+    header(&mut s);
+    for q in nfa.states() {
+        state(&mut s, q, q == nfa.finish);
+    }
+    s.push_str(&format!("[*] --> s{}\n", nfa.start));
+    for rule in nfa.rules() {
+        if rule.label == Label::Epsilon {
+            s.push_str(&format!("s{} -[dashed]-> s{} : ε\n", rule.from, rule.to));
+        } else {
+            s.push_str(&format!(
+                "s{} --> s{} : {}\n",
+                rule.from,
+                rule.to,
+                display_label(rule.label)
+            ));
+        }
+    }
+    s.push_str(&format!("s{} --> [*]\n", nfa.finish));
+    s.push_str("@enduml\n");
+    s
+}
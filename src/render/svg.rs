@@ -0,0 +1,233 @@
+//! SVGの状態遷移図
+//!
+//! 状態の位置は`layout`で決め、描画まで自前で行うので、外部のツールやJSのライブラリなしでそのまま表示できる。
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{
+    dfa::{Dfa, State},
+    nfa::Nfa,
+};
+
+use super::{
+    join_alphabets, join_labels,
+    layout::{self, Edge, Layout},
+};
+
+/// 層の間隔
+const LAYER_DISTANCE: f64 = 110.0;
+/// 層の中の状態の間隔
+const STATE_DISTANCE: f64 = 100.0;
+/// 状態の円の半径
+const RADIUS: f64 = 20.0;
+/// 開始状態を示す矢印の長さ
+const START_ARROW: f64 = 30.0;
+/// 図の周りの余白
+const MARGIN: f64 = 20.0;
+
+type Point = (f64, f64);
+
+pub fn dfa(dfa: &Dfa) -> String {
+    let edges = layout::dfa_edges(dfa, join_alphabets);
+    picture(dfa.start, &dfa.finish_states, &dfa.states(), edges)
+}
+
+pub fn nfa(nfa: &Nfa) -> String {
+    let edges = layout::nfa_edges(nfa, join_labels);
+    let finish = BTreeSet::from([nfa.finish]);
+    picture(nfa.start, &finish, &nfa.states(), edges)
+}
+
+/// SVGのテキストや属性値として使えるようにエスケープする
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// 図に含まれる点を囲む長方形
+struct Bounds {
+    min: Point,
+    max: Point,
+}
+
+impl Bounds {
+    fn include(&mut self, (x, y): Point, margin: f64) {
+        self.min = (self.min.0.min(x - margin), self.min.1.min(y - margin));
+        self.max = (self.max.0.max(x + margin), self.max.1.max(y + margin));
+    }
+}
+
+/// `from`から`to`へ向かう長さ`length`のベクトルを`from`に足した点
+fn towards(from: Point, to: Point, length: f64) -> Point {
+    let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+    let d = dx.hypot(dy);
+    (from.0 + dx / d * length, from.1 + dy / d * length)
+}
+
+fn picture(
+    start: State,
+    finish_states: &BTreeSet<State>,
+    states: &BTreeSet<State>,
+    edges: Vec<Edge>,
+) -> String {
+    let layout = Layout::new(start, states, &edges);
+
+    let centers: BTreeMap<State, Point> = layout
+        .positions
+        .iter()
+        .map(|(state, (layer, index))| {
+            let x = *layer as f64 * LAYER_DISTANCE;
+            let y = layout.offset(*layer, *index) * STATE_DISTANCE;
+            (*state, (x, y))
+        })
+        .collect();
+    let mut bounds = Bounds {
+        min: (f64::INFINITY, f64::INFINITY),
+        max: (f64::NEG_INFINITY, f64::NEG_INFINITY),
+    };
+    for center in centers.values() {
+        bounds.include(*center, RADIUS);
+    }
+
+    let mut body = String::new();
+
+    let (x, y) = centers[&start];
+    bounds.include((x - RADIUS - START_ARROW, y), 0.0);
+    body.push_str(&format!(
+        "  <line class=\"edge\" x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\" marker-end=\"url(#arrow)\"/>\n",
+        x - RADIUS - START_ARROW,
+        y,
+        x - RADIUS,
+        y
+    ));
+
+    for edge in &edges {
+        let from = centers[&edge.from];
+        let to = centers[&edge.to];
+        let (path, label) = if edge.from == edge.to {
+            // 層の中心より下の状態では下に、それ以外では上に輪を描き、同じ層の状態と重ならないようにする
+            let (x, y) = from;
+            let (layer, index) = layout.positions[&edge.from];
+            let sign = if layout.offset(layer, index) > 0.0 {
+                1.0
+            } else {
+                -1.0
+            };
+            let (dx, dy) = (RADIUS * 0.5, RADIUS * 0.87 * sign);
+            let top = y + RADIUS * 2.8 * sign;
+            bounds.include((x, top), RADIUS);
+            // 上に描くときは文字の下端を、下に描くときは文字の上端を輪の外側に合わせる
+            let label_y = if sign < 0.0 {
+                top + RADIUS * 0.2
+            } else {
+                top + RADIUS * 0.2 + 14.0
+            };
+            (
+                format!(
+                    "M {:.1} {:.1} C {:.1} {:.1}, {:.1} {:.1}, {:.1} {:.1}",
+                    x - dx,
+                    y + dy,
+                    x - RADIUS * 1.2,
+                    top,
+                    x + RADIUS * 1.2,
+                    top,
+                    x + dx,
+                    y + dy
+                ),
+                (x, label_y),
+            )
+        } else {
+            let bend = match layout.bend(edge.from, edge.to) {
+                0 => 0.0,
+                1 => 20.0,
+                span => (15.0 + 15.0 * span as f64).min(80.0),
+            };
+            let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+            let d = dx.hypot(dy);
+            let middle = ((from.0 + to.0) / 2.0, (from.1 + to.1) / 2.0);
+            // SVGはy軸が下向きなので、(dy, -dx)が進行方向の左になる
+            let control = (
+                middle.0 + dy / d * bend * 2.0,
+                middle.1 - dx / d * bend * 2.0,
+            );
+            let start = towards(from, control, RADIUS);
+            let end = towards(to, control, RADIUS);
+            bounds.include(control, 0.0);
+            // 2次ベジェ曲線のt=0.5の点
+            let label = (
+                (start.0 + 2.0 * control.0 + end.0) / 4.0,
+                (start.1 + 2.0 * control.1 + end.1) / 4.0 - 6.0,
+            );
+            (
+                format!(
+                    "M {:.1} {:.1} Q {:.1} {:.1}, {:.1} {:.1}",
+                    start.0, start.1, control.0, control.1, end.0, end.1
+                ),
+                label,
+            )
+        };
+        bounds.include(label, RADIUS);
+        let class = if edge.dashed { "edge epsilon" } else { "edge" };
+        body.push_str(&format!(
+            "  <path class=\"{}\" d=\"{}\" marker-end=\"url(#arrow)\"/>\n",
+            class, path
+        ));
+        body.push_str(&format!(
+            "  <text class=\"label\" x=\"{:.1}\" y=\"{:.1}\">{}</text>\n",
+            label.0,
+            label.1,
+            escape(&edge.label)
+        ));
+    }
+
+    for (state, (x, y)) in &centers {
+        body.push_str(&format!(
+            "  <circle class=\"state\" cx=\"{:.1}\" cy=\"{:.1}\" r=\"{:.1}\"/>\n",
+            x, y, RADIUS
+        ));
+        if finish_states.contains(state) {
+            body.push_str(&format!(
+                "  <circle class=\"state\" cx=\"{:.1}\" cy=\"{:.1}\" r=\"{:.1}\"/>\n",
+                x,
+                y,
+                RADIUS - 4.0
+            ));
+        }
+        body.push_str(&format!(
+            "  <text class=\"name\" x=\"{:.1}\" y=\"{:.1}\">{}</text>\n",
+            x, y, state
+        ));
+    }
+
+    let (x, y) = (bounds.min.0 - MARGIN, bounds.min.1 - MARGIN);
+    let width = bounds.max.0 - bounds.min.0 + MARGIN * 2.0;
+    let height = bounds.max.1 - bounds.min.1 + MARGIN * 2.0;
+    let mut s = String::new();
+    s.push_str(&format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{:.0}\" height=\"{:.0}\" viewBox=\"{:.1} {:.1} {:.1} {:.1}\">\n",
+        width.ceil(),
+        height.ceil(),
+        x,
+        y,
+        width,
+        height
+    ));
+    s.push_str("  <defs>\n");
+    s.push_str("    <marker id=\"arrow\" viewBox=\"0 0 10 10\" refX=\"10\" refY=\"5\" markerWidth=\"8\" markerHeight=\"8\" orient=\"auto\">\n");
+    s.push_str("      <path d=\"M 0 0 L 10 5 L 0 10 z\"/>\n");
+    s.push_str("    </marker>\n");
+    s.push_str("  </defs>\n");
+    s.push_str("  <style>\n");
+    s.push_str("    .state { fill: white; stroke: black; stroke-width: 1.5; }\n");
+    s.push_str("    .edge { fill: none; stroke: black; stroke-width: 1.2; }\n");
+    s.push_str("    .epsilon { stroke: gray; stroke-dasharray: 4 3; }\n");
+    s.push_str("    text { font-family: sans-serif; font-size: 14px; text-anchor: middle; }\n");
+    s.push_str("    .name { dominant-baseline: central; }\n");
+    s.push_str("    .label { paint-order: stroke; stroke: white; stroke-width: 4px; }\n");
+    s.push_str("  </style>\n");
+    s.push_str(&body);
+    s.push_str("</svg>\n");
+    s
+}
@@ -0,0 +1,115 @@
+//! TikZの`automata`ライブラリを使った状態遷移図
+//!
+//! 状態の位置は`layout`で自動的に決めるので、手で配置しなくてもそのままコンパイルできる。
+//! 使うには`\usepackage{tikz}`と`\usetikzlibrary{automata, arrows.meta}`、
+//! 記号でない文字を`\text`で書くために`\usepackage{amsmath}`が必要。
+
+use std::collections::BTreeSet;
+
+use crate::{
+    dfa::{Dfa, State},
+    nfa::{Label, Nfa},
+};
+
+use super::{
+    latex::math,
+    layout::{self, Edge, Layout},
+};
+
+/// 層の間隔(cm)
+const LAYER_DISTANCE: f64 = 2.5;
+/// 層の中の状態の間隔(cm)
+const STATE_DISTANCE: f64 = 2.0;
+
+pub fn dfa(dfa: &Dfa) -> String {
+    let edges = layout::dfa_edges(dfa, |alphabets| {
+        alphabets
+            .iter()
+            .map(|c| math(*c))
+            .collect::<Vec<_>>()
+            .join(",")
+    });
+    picture(dfa.start, &dfa.finish_states, &dfa.states(), edges)
+}
+
+pub fn nfa(nfa: &Nfa) -> String {
+    let edges = layout::nfa_edges(nfa, |labels| {
+        labels
+            .iter()
+            .map(|label| match label {
+                Label::Epsilon => "\\varepsilon".to_string(),
+                Label::Symbol(c) => math(*c),
+            })
+            .collect::<Vec<_>>()
+            .join(",")
+    });
+    let finish = BTreeSet::from([nfa.finish]);
+    picture(nfa.start, &finish, &nfa.states(), edges)
+}
+
+fn picture(
+    start: State,
+    finish_states: &BTreeSet<State>,
+    states: &BTreeSet<State>,
+    edges: Vec<Edge>,
+) -> String {
+    let layout = Layout::new(start, states, &edges);
+
+    let mut s = String::new();
+    s.push_str("% \\usepackage{amsmath}\n");
+    s.push_str("% \\usepackage{tikz}\n");
+    s.push_str("% \\usetikzlibrary{automata, arrows.meta}\n");
+    s.push_str("\\begin{tikzpicture}[shorten >=1pt, auto, >={Stealth[round]}]\n");
+    for (state, (layer, index)) in &layout.positions {
+        let mut style = vec!["state"];
+        if *state == start {
+            style.push("initial");
+        }
+        if finish_states.contains(state) {
+            style.push("accepting");
+        }
+        s.push_str(&format!(
+            "  \\node[{}] (q{}) at ({:.2}, {:.2}) {{${}$}};\n",
+            style.join(", "),
+            state,
+            *layer as f64 * LAYER_DISTANCE,
+            // -0.00と出力しないように0.0を足す
+            -layout.offset(*layer, *index) * STATE_DISTANCE + 0.0,
+            state
+        ));
+    }
+
+    s.push_str("  \\path[->]\n");
+    for edge in &edges {
+        let mut style = vec![];
+        if edge.dashed {
+            style.push("dashed".to_string());
+        }
+        if edge.from == edge.to {
+            style.push("loop above".to_string());
+        } else {
+            match layout.bend(edge.from, edge.to) {
+                0 => {}
+                1 => style.push("bend left".to_string()),
+                span => style.push(format!("bend left={}", (15 + 10 * span).min(60))),
+            }
+        }
+        let style = if style.is_empty() {
+            String::new()
+        } else {
+            format!(" [{}]", style.join(", "))
+        };
+        let to = if edge.from == edge.to {
+            String::new()
+        } else {
+            format!("q{}", edge.to)
+        };
+        s.push_str(&format!(
+            "    (q{}) edge{} node {{${}$}} ({})\n",
+            edge.from, style, edge.label, to
+        ));
+    }
+    s.push_str("  ;\n");
+    s.push_str("\\end{tikzpicture}\n");
+    s
+}
@@ -0,0 +1,542 @@
+//! 正規表現からNFA、DFA、最小DFAを作る各構成とNFAのシミュレーションが、正規表現の意味どおりの言語を受理するか調べる
+//!
+//! 正規表現の意味を定義どおりに計算して長さ`MAX_LEN`までの文字列を列挙したものと、
+//! 各構成で作ったオートマトンが受理する文字列を総当たりで比べる。
+
+use std::collections::BTreeSet;
+
+use regend::{
+    dfa::{Dfa, Rule as DfaRule, State},
+    minimize::Split,
+    nfa::{Budget, GlobalEnv, Label, Limits},
+    parser,
+    product::BoolOp,
+    regexpr::{get_alphabets_with, RegExpr},
+    Error,
+};
+
+/// 比べる文字列の長さの上限
+const MAX_LEN: usize = 6;
+
+/// 文字の集合`alphabets`上の長さ`max_len`以下の文字列をすべて列挙する
+fn all_strings(alphabets: &[char], max_len: usize) -> BTreeSet<String> {
+    let mut all = BTreeSet::from([String::new()]);
+    let mut current = vec![String::new()];
+    for _ in 0..max_len {
+        current = current
+            .iter()
+            .flat_map(|s| alphabets.iter().map(move |c| format!("{s}{c}")))
+            .collect();
+        all.extend(current.iter().cloned());
+    }
+    all
+}
+
+/// 2つの言語の連接のうち、長さ`max_len`以下のもの
+fn concat(left: &BTreeSet<String>, right: &BTreeSet<String>, max_len: usize) -> BTreeSet<String> {
+    let mut v = BTreeSet::new();
+    for l in left {
+        for r in right {
+            if l.chars().count() + r.chars().count() <= max_len {
+                v.insert(format!("{l}{r}"));
+            }
+        }
+    }
+    v
+}
+
+/// `language`の0回以上の繰り返しのうち、長さ`max_len`以下のもの
+fn star(language: &BTreeSet<String>, max_len: usize) -> BTreeSet<String> {
+    let mut v = BTreeSet::from([String::new()]);
+    loop {
+        let next: BTreeSet<String> = v.union(&concat(&v, language, max_len)).cloned().collect();
+        if next == v {
+            return v;
+        }
+        v = next;
+    }
+}
+
+/// 正規表現が表す言語のうち、長さ`max_len`以下の文字列を定義どおりに求める。
+/// 糖衣構文も展開せずに直接計算する
+fn language(r: &RegExpr, alphabets: &[char], max_len: usize) -> BTreeSet<String> {
+    let symbols = |chars: Vec<char>| chars.into_iter().map(String::from).collect();
+    match r {
+        RegExpr::Empty => BTreeSet::new(),
+        RegExpr::Epsilon => BTreeSet::from([String::new()]),
+        RegExpr::Char(c) => BTreeSet::from([c.to_string()]),
+        RegExpr::Class { negated, ranges } => symbols(
+            alphabets
+                .iter()
+                .copied()
+                .filter(|c| ranges.iter().any(|(a, b)| (a..=b).contains(&c)) != *negated)
+                .collect(),
+        ),
+        RegExpr::Any => symbols(alphabets.to_vec()),
+        RegExpr::Cat(v) => v.iter().fold(BTreeSet::from([String::new()]), |acc, r| {
+            concat(&acc, &language(r, alphabets, max_len), max_len)
+        }),
+        RegExpr::Or(v) => v
+            .iter()
+            .flat_map(|r| language(r, alphabets, max_len))
+            .collect(),
+        RegExpr::Repeat(r) => star(&language(r, alphabets, max_len), max_len),
+        RegExpr::And(v) => v.iter().fold(all_strings(alphabets, max_len), |acc, r| {
+            acc.intersection(&language(r, alphabets, max_len))
+                .cloned()
+                .collect()
+        }),
+        RegExpr::Not(r) => all_strings(alphabets, max_len)
+            .difference(&language(r, alphabets, max_len))
+            .cloned()
+            .collect(),
+        RegExpr::Plus(r) => {
+            let l = language(r, alphabets, max_len);
+            concat(&l, &star(&l, max_len), max_len)
+        }
+        RegExpr::Optional(r) => {
+            let mut l = language(r, alphabets, max_len);
+            l.insert(String::new());
+            l
+        }
+        RegExpr::Bounded { expr, min, max } => {
+            let l = language(expr, alphabets, max_len);
+            let mut power = BTreeSet::from([String::new()]);
+            for _ in 0..*min {
+                power = concat(&power, &l, max_len);
+            }
+            let mut v = power.clone();
+            match max {
+                Some(max) => {
+                    for _ in *min..*max {
+                        power = concat(&power, &l, max_len);
+                        v.extend(power.iter().cloned());
+                    }
+                }
+                None => v = concat(&power, &star(&l, max_len), max_len),
+            }
+            v
+        }
+    }
+}
+
+/// DFAが受理する長さ`max_len`以下の文字列。表にしたDFAでも同じ結果になるか確かめる
+fn accepted_by(dfa: &Dfa, alphabets: &[char], max_len: usize) -> BTreeSet<String> {
+    let dense = dfa.compile();
+    all_strings(alphabets, max_len)
+        .into_iter()
+        .filter(|s| {
+            let result = dfa.run(s);
+            assert_eq!(dense.run(s), result, "{s}");
+            assert_eq!(dense.accepts(s), result.accepted, "{s}");
+            result.accepted
+        })
+        .collect()
+}
+
+/// 正規表現の節の数
+fn nodes(r: &RegExpr) -> usize {
+    1 + match r {
+        RegExpr::Cat(v) | RegExpr::Or(v) | RegExpr::And(v) => v.iter().map(nodes).sum(),
+        RegExpr::Repeat(r) | RegExpr::Not(r) | RegExpr::Plus(r) | RegExpr::Optional(r) => nodes(r),
+        RegExpr::Bounded { expr, .. } => nodes(expr),
+        _ => 0,
+    }
+}
+
+/// 正規表現`r`から作った各オートマトンが、文字の集合`alphabets`上で正規表現の意味どおりの言語を受理するか調べる
+fn check(r: &RegExpr, extra: &[char]) {
+    let alphabets = get_alphabets_with(&[r], extra);
+    let expected = language(r, &alphabets, MAX_LEN);
+
+    let nfa = r.to_nfa(&mut GlobalEnv::new(&alphabets));
+    let mut simulator = nfa.simulator();
+    let simulated: BTreeSet<String> = all_strings(&alphabets, MAX_LEN)
+        .into_iter()
+        .filter(|s| {
+            let accepted = simulator.accepts(s);
+            let trace = simulator.run_trace(s);
+            assert_eq!(trace.last().unwrap().contains(&nfa.finish), accepted, "{s}");
+            accepted
+        })
+        .collect();
+    assert_eq!(simulated, expected, "NFA simulation: {r}");
+
+    let dfa = r.to_dfa(&alphabets);
+    assert_eq!(accepted_by(&dfa, &alphabets, MAX_LEN), expected, "DFA: {r}");
+
+    assert_eq!(r.desugared_size(), nodes(&r.desugar()), "{r}");
+    let desugared = r.desugar().to_dfa(&alphabets);
+    assert_eq!(
+        accepted_by(&desugared, &alphabets, MAX_LEN),
+        expected,
+        "desugared DFA: {r}"
+    );
+
+    let minimized = dfa.minimize();
+    assert_eq!(
+        accepted_by(&minimized.dfa, &alphabets, MAX_LEN),
+        expected,
+        "minimized DFA: {r}"
+    );
+    // 状態の番号付けも同値類も一致する
+    assert_eq!(minimized, dfa.minimize_moore(), "Hopcroft and Moore: {r}");
+}
+
+fn check_str(s: &str, extra: &str) {
+    let r = parser::parse(s).unwrap_or_else(|e| panic!("{s}: {e}"));
+    check(&r, &parser::parse_alphabet(extra).unwrap());
+}
+
+#[test]
+fn empty_set_and_empty_string() {
+    check(&RegExpr::Empty, &['a']);
+    check(&RegExpr::Epsilon, &['a']);
+    check(&RegExpr::Cat(vec![]), &['a']);
+    check(&RegExpr::Or(vec![]), &['a']);
+    check(&RegExpr::And(vec![]), &['a']);
+    check(&RegExpr::Repeat(Box::new(RegExpr::Cat(vec![]))), &['a']);
+
+    for s in [
+        "φ",
+        "ε",
+        "φ*",
+        "ε*",
+        "aφ",
+        "φa",
+        "φ|a",
+        "ε|a",
+        "(ε|a)b",
+        "(φ|ε)*a",
+        "~φ",
+        "~ε",
+    ] {
+        check_str(s, "ab");
+    }
+}
+
+#[test]
+fn basic_operators() {
+    for s in [
+        "a",
+        "ab",
+        "a|b",
+        "a*",
+        "(a|b)*abb",
+        "(ab|ba)*",
+        "a*b*",
+        "(a*b*)*",
+        "((a|b)(a|b))*",
+        "a(b|c)*a",
+    ] {
+        check_str(s, "");
+    }
+}
+
+#[test]
+fn intersection_and_complement() {
+    for s in [
+        "(a|b)*aa(a|b)* & ~((a|b)*bb(a|b)*)",
+        "~a",
+        "~(a|b)*",
+        "~~a",
+        "a*&b*",
+        "(a|b)*&(ab)*",
+        "~(a*)b",
+    ] {
+        check_str(s, "");
+    }
+}
+
+#[test]
+fn repetition_sugar() {
+    for s in [
+        "a+",
+        "a?b",
+        "(ab)+",
+        "a{0}",
+        "a{2}",
+        "a{1,3}",
+        "(a|b){2,}",
+        "a*+?",
+        "(a?){2}b",
+        "a{0,0}|b",
+    ] {
+        check_str(s, "");
+    }
+}
+
+#[test]
+fn character_classes() {
+    check_str("[a-c]x", "");
+    check_str("[^a]b", "a-c");
+    check_str(".a", "ab");
+    check_str("[^ab]*", "a-c");
+    check_str(".*&~(.*a.*)", "abc");
+}
+
+#[test]
+fn escaped_literals() {
+    check_str("\\*'+'|\\ ", "");
+    check_str("'ab'*", "");
+    check_str("あい*", "");
+    check_str("\\u{3042}|\\(", "");
+    // 文字のεはε遷移と区別される
+    check_str("a\\εb|ε", "");
+    check_str("(\\ε|φ)*", "");
+}
+
+/// 表示した正規表現をパースし直すと、同じ言語を表す
+#[test]
+fn display_round_trip() {
+    for s in [
+        "(a|b)*abb",
+        "a+b?c{1,2}d{2,}",
+        "[^a-c\\-]x.",
+        "\\*'+'\\ \\\\",
+        "~(a&b)|φ|ε",
+        "あ\\u{3044}",
+    ] {
+        let r = parser::parse(s).unwrap();
+        let printed = r.to_string();
+        let reparsed = parser::parse(&printed).unwrap_or_else(|e| panic!("{printed}: {e}"));
+        let alphabets = get_alphabets_with(&[&r], &[]);
+        assert_eq!(
+            language(&r, &alphabets, MAX_LEN - 2),
+            language(&reparsed, &alphabets, MAX_LEN - 2),
+            "{s} => {printed}"
+        );
+    }
+}
+
+/// 部分集合構成法は上限を超えたら中断し、上限に収まるときは上限なしと同じDFAを作る
+/// Thompson構成で`budget`に数える遷移の計算の回数
+fn nfa_steps(r: &RegExpr, alphabets: &[char]) -> usize {
+    let mut budget = Budget::default();
+    r.to_nfa_with_budget(&mut GlobalEnv::new(alphabets), &mut budget)
+        .unwrap();
+    budget.steps()
+}
+
+#[test]
+fn subset_construction_limits() {
+    let r = parser::parse("(a|b)*a(a|b){8}").unwrap();
+    let alphabets = get_alphabets_with(&[&r], &[]);
+    let dfa = r.to_dfa(&alphabets);
+    let states = dfa.states().len();
+    assert!(states > 512);
+    let nfa_steps = nfa_steps(&r, &alphabets);
+
+    let limits = Limits {
+        max_states: Some(100),
+        max_steps: None,
+    };
+    match r.to_dfa_with_limits(&alphabets, limits) {
+        Err(Error::StateExplosion { states, .. }) => assert_eq!(states, 100),
+        other => panic!("{other:?}"),
+    }
+    let limits = Limits {
+        max_states: None,
+        max_steps: Some(nfa_steps + 10),
+    };
+    match r.to_dfa_with_limits(&alphabets, limits) {
+        Err(Error::StateExplosion { steps, .. }) => assert_eq!(steps, nfa_steps + 10),
+        other => panic!("{other:?}"),
+    }
+    // 共通部分や補集合の中で作るDFAにも上限がかかる
+    let negated = RegExpr::Not(Box::new(r.clone()));
+    assert!(matches!(
+        negated.to_dfa_with_limits(&alphabets, limits),
+        Err(Error::StateExplosion { .. })
+    ));
+
+    let limits = Limits {
+        max_states: Some(states),
+        max_steps: Some(nfa_steps + states * alphabets.len()),
+    };
+    assert_eq!(r.to_dfa_with_limits(&alphabets, limits), Ok(dfa));
+}
+
+/// Thompson構成で作るNFAの状態と遷移規則も数え、展開すると大きくなりすぎる正規表現は展開し終える前に中断する
+#[test]
+fn nfa_construction_limits() {
+    let r = parser::parse("(a|b)*a(a|b){3}c?[a-c]+|ε").unwrap();
+    let alphabets = get_alphabets_with(&[&r], &[]);
+    let nfa = r.to_nfa(&mut GlobalEnv::new(&alphabets));
+    assert_eq!(
+        nfa_steps(&r, &alphabets),
+        nfa.states().len() + nfa.rules().len()
+    );
+
+    // パーサーを通さずに作った、展開すると10^9個の文字になる正規表現
+    let bounded = |expr, n| RegExpr::Bounded {
+        expr: Box::new(expr),
+        min: n,
+        max: Some(n),
+    };
+    let r = bounded(bounded(bounded(RegExpr::Char('a'), 1000), 1000), 1000);
+    let limits = Limits {
+        max_states: None,
+        max_steps: Some(10_000),
+    };
+    let mut env = GlobalEnv::new(&['a']);
+    assert!(matches!(
+        r.to_nfa_with_limits(&mut env, limits),
+        Err(Error::StateExplosion { .. })
+    ));
+}
+
+/// 共通部分や補集合の途中で作るDFAも、1つの変換の中で合わせて上限と比べる
+#[test]
+fn limits_are_shared_across_constructions() {
+    let r = parser::parse("(a|b)*a(a|b){3}").unwrap();
+    let alphabets = get_alphabets_with(&[&r], &[]);
+    let states = r.to_dfa(&alphabets).states().len();
+    let limit = |max_states, max_steps| Limits {
+        max_states,
+        max_steps,
+    };
+
+    // 1つずつなら上限に収まるDFAでも、共通部分では両辺のDFAを合わせて数える
+    let limits = limit(Some(states + states / 2), None);
+    assert!(r.to_dfa_with_limits(&alphabets, limits).is_ok());
+    let and = RegExpr::And(vec![r.clone(), r.clone()]);
+    match and.to_dfa_with_limits(&alphabets, limits) {
+        Err(Error::StateExplosion { states: s, .. }) => assert_eq!(s, states + states / 2),
+        other => panic!("{other:?}"),
+    }
+
+    // 補集合をとるために完全にする計算も数える
+    let steps = nfa_steps(&r, &alphabets) + states * alphabets.len();
+    let limits = limit(None, Some(steps));
+    assert!(r.to_dfa_with_limits(&alphabets, limits).is_ok());
+    let not = RegExpr::Not(Box::new(r.clone()));
+    match not.to_dfa_with_limits(&alphabets, limits) {
+        Err(Error::StateExplosion { steps: s, .. }) => assert_eq!(s, steps),
+        other => panic!("{other:?}"),
+    }
+
+    // 等価性判定では両辺のDFAを合わせて数える
+    let limits = limit(Some(states), None);
+    assert!(matches!(
+        r.equivalent_with_limits(&r, &[], limits),
+        Err(Error::StateExplosion { .. })
+    ));
+    let limits = limit(Some(2 * states), None);
+    assert!(r.equivalent_with_limits(&r, &[], limits).is_ok());
+
+    // 直積の状態と遷移の計算も数える
+    let dfa = r.to_dfa(&alphabets);
+    let product = dfa.intersection(&dfa).dfa;
+    let mut budget = Budget::default();
+    let counted = dfa
+        .product_with_budget(&dfa, BoolOp::Intersection, &mut budget)
+        .unwrap()
+        .dfa;
+    assert_eq!(counted, product);
+    assert_eq!(budget.states(), product.states().len());
+    assert_eq!(budget.steps(), product.states().len() * alphabets.len());
+    let mut budget = Budget::new(limit(Some(3), None));
+    assert!(matches!(
+        dfa.product_with_budget(&dfa, BoolOp::Intersection, &mut budget),
+        Err(Error::StateExplosion { states: 3, .. })
+    ));
+}
+
+/// 遷移規則の索引で引いた遷移とε閉包が、遷移規則をすべてなめたものと一致する
+#[test]
+fn indexed_edges_match_rules() {
+    let r = parser::parse("(a|b)*a(a|b){2}&~(ab)|ε").unwrap();
+    let alphabets = get_alphabets_with(&[&r], &[]);
+    let nfa = r.to_nfa(&mut GlobalEnv::new(&alphabets));
+    let labels: Vec<Label> = std::iter::once(Label::Epsilon)
+        .chain(alphabets.iter().map(|c| Label::Symbol(*c)))
+        .collect();
+    for s in nfa.states() {
+        for label in &labels {
+            let expected: BTreeSet<_> = nfa
+                .rules()
+                .iter()
+                .filter(|rule| rule.from == s && rule.label == *label)
+                .map(|rule| rule.to)
+                .collect();
+            assert_eq!(nfa.edge(s, *label), expected, "{s} {label}");
+        }
+        let closure = nfa.closure_(s);
+        assert!(closure.contains(&s));
+        for t in &closure {
+            assert!(nfa.edge(*t, Label::Epsilon).is_subset(&closure));
+        }
+    }
+    // NFAにない状態からは遷移しない
+    assert!(nfa.edge(-1, Label::Epsilon).is_empty());
+}
+
+/// (a|b)*abbの部分集合構成で得られる5状態のDFAについて、最小化の初期分割と各分割を確かめる。
+/// 状態4だけが'b'で受理状態5へ、状態2だけが'b'で状態4へ遷移する
+#[test]
+fn minimization_trace() {
+    let set = |states: &[State]| states.iter().copied().collect::<BTreeSet<_>>();
+    let r = parser::parse("(a|b)*abb").unwrap();
+    let dfa = r.to_dfa(&get_alphabets_with(&[&r], &[]));
+    let (minimized, trace) = dfa.minimize_with_trace();
+    assert_eq!(minimized, dfa.minimize());
+    assert_eq!(trace.sink, None);
+    assert_eq!(trace.initial, [set(&[5]), set(&[1, 2, 3, 4])]);
+
+    let splits: Vec<&Split> = trace.rounds.iter().flat_map(|r| &r.splits).collect();
+    assert_eq!(
+        splits,
+        [
+            &Split {
+                alphabet: 'b',
+                block: set(&[1, 2, 3, 4]),
+                inside: set(&[4]),
+                outside: set(&[1, 2, 3]),
+            },
+            &Split {
+                alphabet: 'b',
+                block: set(&[1, 2, 3]),
+                inside: set(&[2]),
+                outside: set(&[1, 3]),
+            },
+        ]
+    );
+    // 各ラウンドは、直前までの分割が反映された分割から始まる
+    assert_eq!(trace.rounds[0].splitter, set(&[5]));
+    assert_eq!(trace.rounds[0].partition, trace.initial);
+    assert_eq!(
+        trace.rounds[1].partition,
+        [set(&[5]), set(&[4]), set(&[1, 2, 3])]
+    );
+    // 1と3だけが同値
+    assert_eq!(minimized.classes[&1], minimized.classes[&3]);
+    assert_eq!(minimized.dfa.states().len(), 4);
+}
+
+/// 遷移規則が欠けたDFAでは、補った死に状態を報告する
+#[test]
+fn minimization_trace_sink() {
+    let dfa = Dfa {
+        start: 1,
+        finish_states: BTreeSet::from([2]),
+        rules: BTreeSet::from([
+            DfaRule {
+                from: 1,
+                alphabet: 'a',
+                to: 2,
+            },
+            DfaRule {
+                from: 2,
+                alphabet: 'b',
+                to: 1,
+            },
+        ]),
+    };
+    let (minimized, trace) = dfa.minimize_with_trace();
+    assert_eq!(trace.sink, Some(3));
+    assert_eq!(trace.initial, [BTreeSet::from([2]), BTreeSet::from([1, 3])]);
+    // 死に状態は元のDFAの状態ではないので、同値類に含めない
+    assert!(!minimized.classes.contains_key(&3));
+    assert_eq!(minimized, dfa.minimize());
+}
@@ -0,0 +1,119 @@
+//! 2つの正規表現の等価性判定が、最短の反例をどちらが受理するかまで正しく返すか調べる
+
+use regend::{
+    equiv::Equivalence,
+    parser,
+    regexpr::{get_alphabets_with, RegExpr},
+};
+
+/// 比べる文字列の長さの上限
+const MAX_LEN: usize = 6;
+
+fn parse(s: &str) -> RegExpr {
+    parser::parse(s).unwrap_or_else(|e| panic!("{s}: {e}"))
+}
+
+fn equivalent(left: &str, right: &str, alphabet: &str) -> Equivalence {
+    parse(left).equivalent(&parse(right), &parser::parse_alphabet(alphabet).unwrap())
+}
+
+/// 長さの短い順、同じ長さなら辞書順に、文字の集合`alphabets`上の長さ`max_len`以下の文字列を並べる
+fn all_strings(alphabets: &[char], max_len: usize) -> Vec<String> {
+    let mut all = vec![String::new()];
+    let mut current = vec![String::new()];
+    for _ in 0..max_len {
+        current = current
+            .iter()
+            .flat_map(|s| alphabets.iter().map(move |c| format!("{s}{c}")))
+            .collect();
+        all.extend(current.iter().cloned());
+    }
+    all
+}
+
+/// 総当たりで反例を探し、判定結果が最短の反例と受理する側を正しく返しているか調べる
+fn check(left: &str, right: &str, alphabet: &str) {
+    let (l, r) = (parse(left), parse(right));
+    let alphabets = get_alphabets_with(&[&l, &r], &parser::parse_alphabet(alphabet).unwrap());
+    let (left_dfa, right_dfa) = (l.to_dfa(&alphabets), r.to_dfa(&alphabets));
+    let shortest = all_strings(&alphabets, MAX_LEN)
+        .into_iter()
+        .find(|s| left_dfa.run(s).accepted != right_dfa.run(s).accepted);
+
+    match (equivalent(left, right, alphabet), shortest) {
+        (Equivalence::Equivalent, None) => {}
+        (
+            Equivalence::Different {
+                witness,
+                accepted_by_left,
+            },
+            Some(shortest),
+        ) => {
+            assert_eq!(
+                witness.chars().count(),
+                shortest.chars().count(),
+                "{left} vs {right}: {witness:?} is not shortest"
+            );
+            assert_eq!(left_dfa.run(&witness).accepted, accepted_by_left);
+            assert_eq!(right_dfa.run(&witness).accepted, !accepted_by_left);
+        }
+        (result, shortest) => panic!("{left} vs {right}: {result:?}, {shortest:?}"),
+    }
+}
+
+#[test]
+fn empty_string_accepted_only_by_right() {
+    assert_eq!(
+        equivalent("a*b", "(a|b)*", ""),
+        Equivalence::Different {
+            witness: String::new(),
+            accepted_by_left: false,
+        }
+    );
+}
+
+/// 否定の文字クラスは`alphabet`の文字も含めて考える
+#[test]
+fn depends_on_alphabet() {
+    assert_eq!(equivalent("[^a]", "b", ""), Equivalence::Equivalent);
+    assert_eq!(
+        equivalent("[^a]", "b", "abc"),
+        Equivalence::Different {
+            witness: "c".to_string(),
+            accepted_by_left: true,
+        }
+    );
+}
+
+#[test]
+fn equivalent_expressions() {
+    for (left, right) in [
+        ("(a|b)*", "(a*b*)*"),
+        ("a+", "aa*"),
+        ("(ab)*a", "a(ba)*"),
+        ("a{2,3}", "aa|aaa"),
+        ("~~(a|b)", "a|b"),
+        ("φ*", "ε"),
+    ] {
+        assert_eq!(
+            equivalent(left, right, ""),
+            Equivalence::Equivalent,
+            "{left} vs {right}"
+        );
+    }
+}
+
+#[test]
+fn shortest_witness() {
+    for (left, right, alphabet) in [
+        ("a*b", "(a|b)*", ""),
+        ("(a|b)*abb", "(a|b)*bb", ""),
+        ("(a|b)*bb", "(a|b)*abb", ""),
+        ("a{3,}", "a{4,}", ""),
+        ("(ab)*", "(ab|ba)*", ""),
+        (".*a.*", "~(b*)", "abc"),
+        ("(a|b)*", "(a|b)*", ""),
+    ] {
+        check(left, right, alphabet);
+    }
+}
@@ -0,0 +1,152 @@
+//! パースエラーの診断が、誤りの箇所と修正方法を正しく示すか調べる
+
+use regend::{diagnostic::Diagnostic, parser, Error};
+
+fn diagnose(s: &str) -> Diagnostic {
+    match parser::parse(s) {
+        Err(Error::Parse(d)) => d,
+        r => panic!("{s}: {r:?}"),
+    }
+}
+
+/// 範囲`start..end`、列番号`column`、メッセージ`message`、提案`help`を確かめる
+fn check(s: &str, start: usize, end: usize, column: usize, message: &str, help: Option<&str>) {
+    let d = diagnose(s);
+    assert_eq!(
+        (d.start, d.end, d.column),
+        (start, end, column),
+        "{s}: {d:?}"
+    );
+    assert_eq!(d.message, message, "{s}");
+    assert_eq!(d.help.as_deref(), help, "{s}");
+}
+
+/// 閉じられていない`(`は、閉じられていない中で最も内側のものを指す
+#[test]
+fn unclosed_paren() {
+    let help = Some("対応する')'を追加してください");
+    check("(a", 0, 1, 1, "列1で開いた'('が閉じられていない", help);
+    check("x(y(z", 3, 4, 4, "列4で開いた'('が閉じられていない", help);
+    check("((a)", 0, 1, 1, "列1で開いた'('が閉じられていない", help);
+    // 列番号は文字単位で数える
+    check("あ(い", 3, 4, 2, "列2で開いた'('が閉じられていない", help);
+    // `(`の直後で入力が終わる
+    check("(", 0, 1, 1, "列1で開いた'('が閉じられていない", help);
+    check("ab(", 2, 3, 3, "列3で開いた'('が閉じられていない", help);
+    check("a( ", 1, 2, 2, "列2で開いた'('が閉じられていない", help);
+    check("((", 1, 2, 2, "列2で開いた'('が閉じられていない", help);
+    check("a)", 1, 2, 2, "対応する'('がない')'", None);
+}
+
+/// 正規表現が来るべき位置の演算子は、前後どちらに正規表現がないかを示す
+#[test]
+fn stray_operator() {
+    check("*a", 0, 1, 1, "'*'の前に繰り返す正規表現がない", None);
+    check("(+)", 1, 2, 2, "'+'の前に繰り返す正規表現がない", None);
+    check("|a", 0, 1, 1, "'|'の前に正規表現がない", None);
+    check("a|", 1, 2, 2, "'|'の後に正規表現がない", None);
+    check("a | | b", 2, 3, 3, "'|'の後に正規表現がない", None);
+    check("a&~", 2, 3, 3, "'~'の後に正規表現がない", None);
+    check(
+        "()",
+        0,
+        2,
+        1,
+        "括弧の中が空",
+        Some("空文字列はεと書いてください"),
+    );
+    check(
+        " ",
+        0,
+        1,
+        1,
+        "正規表現が空",
+        Some("空文字列はε、何も受理しない正規表現はφと書いてください"),
+    );
+}
+
+#[test]
+fn escape_and_class() {
+    check(
+        "a\\q",
+        1,
+        3,
+        2,
+        "不明なエスケープ'\\q'",
+        Some("\\の後に書けるのは記号、空白、n、t、u{...}です"),
+    );
+    check(
+        "[a-]",
+        2,
+        3,
+        3,
+        "'-'の後に範囲の終わりがない",
+        Some("範囲はa-zのように書いてください"),
+    );
+    check(
+        "[b-a]",
+        1,
+        4,
+        2,
+        "範囲b-aの始まりが終わりより後にある",
+        None,
+    );
+    check(
+        "'ab",
+        0,
+        1,
+        1,
+        "'で囲んだ文字列が閉じられていない",
+        Some("文字列の終わりに'を追加してください"),
+    );
+    check(
+        "a{3,2}",
+        1,
+        6,
+        2,
+        "繰り返しの回数の下限3が上限2より大きい",
+        None,
+    );
+}
+
+#[test]
+fn alphabet() {
+    let Err(Error::Parse(d)) = parser::parse_alphabet("a-z]") else {
+        panic!();
+    };
+    assert_eq!((d.start, d.end), (3, 4));
+    assert_eq!(d.help.as_deref(), Some("文字の]は\\]と書いてください"));
+    assert_eq!(
+        parser::parse_alphabet(" a-c x ").unwrap(),
+        ['a', 'b', 'c', 'x']
+    );
+}
+
+/// 繰り返しの回数が多すぎれば、展開する前にパースエラーにする
+#[test]
+fn repetition_limit() {
+    let message = format!("繰り返しの回数が上限の{}を超えている", parser::MAX_REPEAT);
+    let help = Some("回数を減らすか、*や+を使ってください");
+    check("a{99999999}", 1, 11, 2, &message, help);
+    check("a{1,1001}", 1, 9, 2, &message, help);
+    check("a{1001,}", 1, 8, 2, &message, help);
+    // usizeに収まらない回数
+    check("a{18446744073709551616}", 1, 23, 2, &message, help);
+    assert!(parser::parse("a{1000}").is_ok());
+    assert!(parser::parse("a{0,1000}").is_ok());
+}
+
+/// 入れ子の繰り返しは回数の積だけ大きくなるので、展開した後の大きさも制限する
+#[test]
+fn expansion_limit() {
+    let message = format!(
+        "繰り返しを展開すると正規表現の大きさが上限の{}を超える",
+        parser::MAX_EXPANDED
+    );
+    let help = Some("回数を減らすか、*や+を使ってください");
+    check("((a{1000}){1000}){1000}", 10, 16, 11, &message, help);
+    check("((a{1000}){1000})", 10, 16, 11, &message, help);
+    check("(a{1000}) {100}", 10, 15, 11, &message, help);
+    let r = parser::parse("(a{1000}){99}").unwrap();
+    assert!(r.desugared_size() <= parser::MAX_EXPANDED);
+}
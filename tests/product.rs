@@ -0,0 +1,112 @@
+//! 直積オートマトンが集合演算どおりの言語を受理し、状態の組を正しく記録しているか調べる
+
+use regend::{
+    dfa::{Dfa, State},
+    parser,
+    product::BoolOp,
+    regexpr::get_alphabets_with,
+};
+
+/// 比べる文字列の長さの上限
+const MAX_LEN: usize = 5;
+
+const OPS: [BoolOp; 4] = [
+    BoolOp::Intersection,
+    BoolOp::Union,
+    BoolOp::Difference,
+    BoolOp::SymmetricDifference,
+];
+
+/// 正規表現に現れる文字だけの上でDFAにする。左右で文字の集合が違うこともある
+fn dfa(s: &str) -> Dfa {
+    let r = parser::parse(s).unwrap_or_else(|e| panic!("{s}: {e}"));
+    r.to_dfa(&get_alphabets_with(&[&r], &[]))
+}
+
+/// 文字の集合`alphabets`上の長さ`max_len`以下の文字列をすべて列挙する
+fn all_strings(alphabets: &[char], max_len: usize) -> Vec<String> {
+    let mut all = vec![String::new()];
+    let mut current = vec![String::new()];
+    for _ in 0..max_len {
+        current = current
+            .iter()
+            .flat_map(|s| alphabets.iter().map(move |c| format!("{s}{c}")))
+            .collect();
+        all.extend(current.iter().cloned());
+    }
+    all
+}
+
+/// 文字列を読み込ませた後の状態。行き詰まったら`None`
+fn last_state(dfa: &Dfa, input: &str) -> Option<State> {
+    let result = dfa.run(input);
+    if result.stuck_at.is_some() {
+        return None;
+    }
+    Some(result.path.last().map_or(dfa.start, |(_, _, to)| *to))
+}
+
+fn check(left: &str, right: &str) {
+    let (l, r) = (dfa(left), dfa(right));
+    let alphabets: Vec<char> = l.alphabets().union(&r.alphabets()).copied().collect();
+    for op in OPS {
+        let product = l.product(&r, op);
+        assert_eq!(
+            product.pairs[&product.dfa.start],
+            (Some(l.start), Some(r.start))
+        );
+        for s in all_strings(&alphabets, MAX_LEN) {
+            let result = product.dfa.run(&s);
+            // 揃えた文字の集合の上では行き詰まらない
+            assert_eq!(result.stuck_at, None, "{left} {op:?} {right}: {s:?}");
+            assert_eq!(
+                result.accepted,
+                op.apply(l.run(&s).accepted, r.run(&s).accepted),
+                "{left} {op:?} {right}: {s:?}"
+            );
+            // 直積DFAの状態は、左右のDFAがそれぞれいる状態の組に対応する
+            let state = last_state(&product.dfa, &s).unwrap();
+            assert_eq!(
+                product.pairs[&state],
+                (last_state(&l, &s), last_state(&r, &s)),
+                "{left} {op:?} {right}: {s:?}"
+            );
+        }
+    }
+}
+
+#[test]
+fn same_alphabet() {
+    for (left, right) in [
+        ("(a|b)*aa(a|b)*", "(a|b)*bb(a|b)*"),
+        ("a*b", "(a|b)*"),
+        ("(ab)*", "a(ba)*b|ε"),
+        ("(a|b)*a", "(a|b)*b"),
+    ] {
+        check(left, right);
+    }
+}
+
+/// 片方にしか現れない文字では、もう片方は行き詰まる
+#[test]
+fn different_alphabets() {
+    for (left, right) in [("a*", "b*"), ("a(a|c)*", "ab*"), ("ε", "c")] {
+        check(left, right);
+    }
+}
+
+#[test]
+fn combinators() {
+    let (l, r) = (dfa("a*"), dfa("aa"));
+    assert_eq!(l.intersection(&r), l.product(&r, BoolOp::Intersection));
+    assert_eq!(l.union(&r), l.product(&r, BoolOp::Union));
+    assert_eq!(l.difference(&r), l.product(&r, BoolOp::Difference));
+    assert_eq!(
+        l.symmetric_difference(&r),
+        l.product(&r, BoolOp::SymmetricDifference)
+    );
+    let difference = l.difference(&r).dfa;
+    assert!(difference.run("a").accepted);
+    assert!(!difference.run("aa").accepted);
+    assert!(difference.run("aaa").accepted);
+}
@@ -0,0 +1,217 @@
+//! 各形式の出力に、状態や遷移が期待どおりに含まれるか調べる
+
+use regend::{
+    nfa::GlobalEnv,
+    parser,
+    regexpr::get_alphabets_with,
+    render::{self, Format, Graph},
+};
+
+/// 正規表現`s`から作った`graph`を`format`の形式で出力する
+fn render(s: &str, graph: Graph, format: Format) -> String {
+    let r = parser::parse(s).unwrap();
+    let alphabets = get_alphabets_with(&[&r], &[]);
+    let nfa = r.to_nfa(&mut GlobalEnv::new(&alphabets));
+    let dfa = nfa.to_dfa(&alphabets);
+    render::render(&r, &nfa, &dfa, graph, format).unwrap()
+}
+
+/// 文字の`ε`の遷移はε遷移と区別できるように`\ε`と表示する
+#[test]
+fn epsilon_symbol_is_escaped() {
+    for format in [
+        Format::Ascii,
+        Format::Mermaid,
+        Format::PlantUml,
+        Format::Svg,
+    ] {
+        let s = render("a\\εb", Graph::Nfa, format);
+        assert!(s.contains("\\ε"), "{format:?}:\n{s}");
+        let s = render("ab", Graph::Nfa, format);
+        assert!(!s.contains("\\ε"), "{format:?}:\n{s}");
+    }
+    let s = render("a\\εb", Graph::Nfa, Format::Dot);
+    assert!(s.contains("label=\"\\\\ε\""), "{s}");
+    let s = render("a\\εb", Graph::Ast, Format::Dot);
+    assert!(s.contains("label=\"\\\\ε\""), "{s}");
+    let s = render("aε", Graph::Ast, Format::Dot);
+    assert!(!s.contains("\\ε"), "{s}");
+    for format in [Format::Tikz, Format::LatexTable] {
+        let s = render("a\\εb", Graph::Dfa, format);
+        assert!(s.contains("\\textbackslash ε"), "{format:?}:\n{s}");
+        let s = render("ab", Graph::Dfa, format);
+        assert!(!s.contains("\\textbackslash"), "{format:?}:\n{s}");
+    }
+}
+
+#[test]
+fn dot() {
+    assert_eq!(
+        render("a*b", Graph::MinDfa, Format::Dot),
+        r#"digraph DFA {
+    rankdir=LR;
+    node [shape=circle];
+    __start [shape=point];
+    __start -> 1;
+    2 [shape=doublecircle];
+    1 -> 1 [label="a"];
+    1 -> 2 [label="b"];
+    2 -> 3 [label="a,b"];
+    3 -> 3 [label="a,b"];
+}
+"#
+    );
+    let nfa = render("a*b", Graph::Nfa, Format::Dot);
+    assert!(nfa.starts_with("digraph NFA {\n"), "{nfa}");
+    assert_eq!(nfa.matches("style=dashed").count(), 7, "{nfa}");
+    let ast = render("a|b", Graph::Ast, Format::Dot);
+    assert!(ast.contains("[label=\"|\"]"), "{ast}");
+    assert!(ast.contains("[label=\"a\", shape=box]"), "{ast}");
+}
+
+#[test]
+fn mermaid() {
+    assert_eq!(
+        render("a*b", Graph::MinDfa, Format::Mermaid),
+        "stateDiagram-v2
+    direction LR
+    classDef accepting stroke-width:4px
+    state \"1\" as s1
+    state \"2\" as s2
+    class s2 accepting
+    state \"3\" as s3
+    [*] --> s1
+    s1 --> s1: a
+    s1 --> s2: b
+    s2 --> s3: a,b
+    s3 --> s3: a,b
+    s2 --> [*]
+"
+    );
+}
+
+#[test]
+fn plantuml() {
+    assert_eq!(
+        render("a*b", Graph::MinDfa, Format::PlantUml),
+        "@startuml
+hide empty description
+left to right direction
+state \"1\" as s1
+state \"2\" as s2 ##[bold]
+state \"3\" as s3
+[*] --> s1
+s1 --> s1 : a
+s1 --> s2 : b
+s2 --> s3 : a,b
+s3 --> s3 : a,b
+s2 --> [*]
+@enduml
+"
+    );
+}
+
+#[test]
+fn tikz() {
+    let s = render("a(a|b)*", Graph::Dfa, Format::Tikz);
+    assert!(s.contains("\\begin{tikzpicture}"), "{s}");
+    assert!(s.ends_with("\\end{tikzpicture}\n"), "{s}");
+    assert_eq!(s.matches("\\node[state").count(), 5, "{s}");
+    assert!(
+        s.contains("\\node[state, initial] (q1) at (0.00, 0.00) {$1$};"),
+        "{s}"
+    );
+    assert_eq!(s.matches("accepting").count(), 3, "{s}");
+    assert!(s.contains("(q3) edge [loop above] node {$a,b$} ()"), "{s}");
+    // 互いに逆向きの遷移は重ならないように曲げる
+    assert!(s.contains("(q4) edge [bend left] node {$b$} (q5)"), "{s}");
+    assert!(s.contains("(q5) edge [bend left] node {$a$} (q4)"), "{s}");
+
+    let s = render("ab", Graph::Nfa, Format::Tikz);
+    assert!(s.contains("[dashed] node {$\\varepsilon$}"), "{s}");
+}
+
+#[test]
+fn latex_table() {
+    assert_eq!(
+        render("a*b", Graph::MinDfa, Format::LatexTable),
+        "\\begin{tabular}{r|cc}
+ & $a$ & $b$ \\\\
+\\hline
+$\\rightarrow 1$ & $1$ & $2$ \\\\
+$* 2$ & $3$ & $3$ \\\\
+$3$ & $3$ & $3$ \\\\
+\\end{tabular}
+"
+    );
+}
+
+#[test]
+fn svg() {
+    let s = render("a(a|b)*", Graph::Dfa, Format::Svg);
+    assert!(
+        s.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""),
+        "{s}"
+    );
+    assert!(s.ends_with("</svg>\n"), "{s}");
+    // 状態ごとに1つ、受理状態はもう1つ円を描く
+    assert_eq!(s.matches("<circle class=\"state\"").count(), 5 + 3, "{s}");
+    // 開始状態の矢印と遷移ごとの矢印
+    assert_eq!(
+        s.matches("marker-end=\"url(#arrow)\"").count(),
+        1 + 9,
+        "{s}"
+    );
+    assert!(s.contains(">a,b</text>"), "{s}");
+
+    let s = render("a<b", Graph::Nfa, Format::Svg);
+    assert!(s.contains("&lt;"), "{s}");
+    assert!(s.contains("class=\"edge epsilon\""), "{s}");
+}
+
+#[test]
+fn ascii() {
+    assert_eq!(
+        render("a*b", Graph::MinDfa, Format::Ascii),
+        "    ┌a┐                       ┌a,b┐
+    │ ▼                       │   ▼
+   ┌┴────┐       ╔═══╗       ┌┴──────┐
+──▶│  1  │──b───▶║ 2 ║─a,b──▶│   3   │
+   └─────┘       ╚═══╝       └───────┘
+
+"
+    );
+}
+
+/// 状態が多すぎたり幅が広すぎたりするときは、遷移規則の一覧にする
+#[test]
+fn ascii_falls_back_to_list() {
+    // 状態が多すぎる
+    let s = render("(a|b)*a(a|b){4}", Graph::Dfa, Format::Ascii);
+    assert!(s.starts_with("== DFA ==\n"), "{s}");
+    let s = render("(a|b)*a(a|b){4}", Graph::Nfa, Format::Ascii);
+    assert!(s.starts_with("== NFA ==\n"), "{s}");
+    // ラベルが長く幅が広すぎる
+    let s = render("[a-z][a-z]", Graph::MinDfa, Format::Ascii);
+    assert!(s.starts_with("== DFA ==\n"), "{s}");
+    // 小さければ図にする
+    let s = render("ab", Graph::MinDfa, Format::Ascii);
+    assert!(s.contains("──▶│"), "{s}");
+}
+
+#[test]
+fn unsupported_graph() {
+    let r = parser::parse("a").unwrap();
+    let nfa = r.to_nfa(&mut GlobalEnv::new(&['a']));
+    let dfa = nfa.to_dfa(&['a']);
+    for (format, graph) in [
+        (Format::Mermaid, Graph::Ast),
+        (Format::Svg, Graph::Ast),
+        (Format::LatexTable, Graph::Nfa),
+    ] {
+        assert_eq!(
+            render::render(&r, &nfa, &dfa, graph, format),
+            Err(regend::Error::UnsupportedGraph { format, graph })
+        );
+    }
+}